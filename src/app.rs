@@ -1,9 +1,14 @@
 use crate::persistence::StorableState;
+use crate::polymodo::EventLoop;
 use bincode::{Decode, Encode};
 use smol::channel::TrySendError;
+use std::collections::VecDeque;
 use std::future::Future;
 use std::marker::PhantomData;
 use std::mem::MaybeUninit;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 pub type AppKey = u32;
 
@@ -17,13 +22,49 @@ pub trait App: Sized {
 
     const NAME: AppName;
 
-    fn create(message_sender: AppSender<Self::Message>) -> Self;
+    fn create(message_sender: AppSender<Self::Message>, options: &AppOptions) -> Self;
 
     #[allow(unused_variables)]
     fn on_message(&mut self, message: Self::Message) {
         // do nothing by default.
     }
 
+    /// Show or hide the app's busy indicator, as requested through [AppSender::set_busy].
+    fn set_busy(&mut self, _busy: bool) {
+        // apps without a busy indicator have nothing to show.
+    }
+
+    /// A snapshot of the app's internal state, for diagnostics.
+    fn serialize_state(&self) -> serde_json::Value {
+        serde_json::Value::Null
+    }
+
+    /// What the app's window currently shows, for debugging. `None` if it has no window, or it
+    /// couldn't be captured.
+    fn screenshot(&self) -> Option<Screenshot> {
+        None
+    }
+
+    /// Hide the app instead of stopping it, so that it opens instantly the next time it is
+    /// spawned, through [App::reopen]. Returns what [App::stop] would have, or `None` if the app
+    /// should be stopped after all.
+    fn hide(&mut self) -> Option<Self::Output> {
+        // apps are cheap to create, unless they say otherwise.
+        None
+    }
+
+    /// Show an app that was hidden with [App::hide] again, as if it was just created with
+    /// these options.
+    fn reopen(&mut self, _options: &AppOptions) {
+        // only apps that hide themselves are ever reopened.
+    }
+
+    /// Whether a hidden app can still be reopened. If not (e.g. because the output its window was
+    /// shown on is gone), it is stopped, and a new one is created in its place.
+    fn can_reopen(&self) -> bool {
+        true
+    }
+
     fn stop(self) -> Self::Output;
 }
 
@@ -47,6 +88,21 @@ pub trait AppExt: App {
 
         crate::persistence::write_state(app_name.as_str(), state_name, state)
     }
+
+    /// See [crate::persistence::read_config].
+    fn read_config<S>() -> std::io::Result<S>
+    where
+        S: StorableState + serde::de::DeserializeOwned + Default,
+    {
+        crate::persistence::read_config(Self::NAME.config_dir_name(), S::NAME)
+    }
+
+    fn write_config<S>(config: &S) -> std::io::Result<()>
+    where
+        S: StorableState + serde::Serialize,
+    {
+        crate::persistence::write_config(Self::NAME.config_dir_name(), S::NAME, config)
+    }
 }
 
 impl<A: App> AppExt for A {}
@@ -62,6 +118,19 @@ pub trait AppDriver {
 
     fn on_message(&mut self, message: Box<dyn std::any::Any>);
 
+    fn set_busy(&mut self, busy: bool);
+
+    fn serialize_state(&self) -> serde_json::Value;
+
+    fn screenshot(&self) -> Option<Screenshot>;
+
+    /// Hide the driven application, see [App::hide]. If this returns `None`, it should be stopped.
+    fn hide(&mut self) -> Option<Box<dyn AppResult + Send>>;
+
+    fn reopen(&mut self, options: &AppOptions);
+
+    fn can_reopen(&self) -> bool;
+
     /// Stop the driven application. This mirrors [App]'s `stop` function, but is non-consuming.
     /// This is because `AppDriver` is meant to be used as a dynamic trait object, on which methods
     /// accepting `self` (instead of a reference) cannot be called.
@@ -109,6 +178,41 @@ where
             .on_message(*message);
     }
 
+    fn set_busy(&mut self, busy: bool) {
+        self.app
+            .as_mut()
+            .expect("app has been stopped")
+            .set_busy(busy);
+    }
+
+    fn serialize_state(&self) -> serde_json::Value {
+        self.app
+            .as_ref()
+            .map(App::serialize_state)
+            .unwrap_or_default()
+    }
+
+    fn screenshot(&self) -> Option<Screenshot> {
+        self.app.as_ref().and_then(App::screenshot)
+    }
+
+    fn hide(&mut self) -> Option<Box<dyn AppResult + Send>> {
+        let result = self.app.as_mut().expect("app has been stopped").hide()?;
+
+        Some(Box::new(result))
+    }
+
+    fn reopen(&mut self, options: &AppOptions) {
+        self.app
+            .as_mut()
+            .expect("app has been stopped")
+            .reopen(options);
+    }
+
+    fn can_reopen(&self) -> bool {
+        self.app.as_ref().is_some_and(App::can_reopen)
+    }
+
     fn stop(&mut self) -> Box<dyn AppResult + Send> {
         let app = self.app.take().expect("app has been already been stopped");
 
@@ -125,58 +229,242 @@ where
     AppDriverImpl::new(app)
 }
 
+/// Send the events in `queue` one by one, waiting for room in the channel, until it is empty.
+async fn drain_queue(sender: smol::channel::Sender<AppEvent>, queue: Arc<Mutex<SendQueue>>) {
+    loop {
+        let event = {
+            let mut queue = queue.lock().unwrap();
+            match queue.events.pop_front() {
+                Some(event) => event,
+                None => {
+                    queue.draining = false;
+                    return;
+                }
+            }
+        };
+
+        if sender.send(event).await.is_err() {
+            log::error!("tried sending message to app, but the message receiver has been dropped: is polymodo dead?");
+
+            let mut queue = queue.lock().unwrap();
+            queue.events.clear();
+            queue.draining = false;
+            return;
+        }
+    }
+}
+
 /// The sender end of a channel for apps to send messages to themselves.
-#[derive(Clone)]
 pub struct AppSender<M> {
     sender: smol::channel::Sender<AppEvent>,
+    /// For everything but the app's own messages, which must never be discarded
+    control: smol::channel::Sender<AppEvent>,
     app_key: AppKey,
+    event_loop: EventLoop,
+    /// The message waiting to be sent by [AppSender::send_debounced], if any
+    debounced: Arc<Mutex<Option<AbortOnDrop>>>,
+    /// Shared by all clones, so that no message overtakes one queued before it
+    queue: Arc<Mutex<SendQueue>>,
     data: PhantomData<M>,
 }
 
+/// The messages [AppSender::send_or_queue] is waiting to send, oldest first.
+#[derive(Default)]
+struct SendQueue {
+    events: VecDeque<AppEvent>,
+    /// Whether a task is sending the queued events. Until it is done, new events are queued
+    /// behind them, even if there is room in the channel.
+    draining: bool,
+}
+
+impl<M> Clone for AppSender<M> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            control: self.control.clone(),
+            app_key: self.app_key,
+            event_loop: self.event_loop,
+            // each clone debounces its own messages
+            debounced: Default::default(),
+            queue: Arc::clone(&self.queue),
+            data: PhantomData,
+        }
+    }
+}
+
 impl<M> AppSender<M>
 where
     M: Send + 'static,
 {
-    pub fn new(app_key: AppKey, sender: smol::channel::Sender<AppEvent>) -> AppSender<M> {
+    pub fn new(
+        app_key: AppKey,
+        sender: smol::channel::Sender<AppEvent>,
+        control: smol::channel::Sender<AppEvent>,
+        event_loop: EventLoop,
+    ) -> AppSender<M> {
         Self {
             sender,
+            control,
             app_key,
+            event_loop,
+            debounced: Default::default(),
+            queue: Default::default(),
             data: Default::default(),
         }
     }
 
-    fn send_event(&self, message: AppMessage) -> Result<(), TrySendError<AppEvent>> {
-        self.sender.try_send(AppEvent {
+    /// Send an event other than a message on the control channel, which is unbounded: unlike
+    /// messages, these are never discarded.
+    fn send_control(&self, message: AppMessage) -> Result<(), TrySendError<AppEvent>> {
+        self.control.try_send(AppEvent {
             app_key: self.app_key,
             message,
         })
     }
 
     pub fn spawn<T: 'static + Send>(&self, fut: impl Future<Output = T> + 'static) {
-        let join_handle = slint::spawn_local(fut).expect("an event loop");
-        let message = AppMessage::SpawnLocal(AbortOnDrop::new(Box::new(join_handle)));
+        let join_handle = self.event_loop.spawn(async move {
+            fut.await;
+        });
+        let message = AppMessage::SpawnLocal(AbortOnDrop::new(join_handle));
 
-        if self.send_event(message).is_err() {
-            log::error!("tried sending a task to polymodo, but the message receiver has been dropped; is polymodo dead?");
+        if let Err(e) = self.send_control(message) {
+            log::error!("couldn't send a task to polymodo ({e}); is polymodo dead?");
         };
     }
 
     /// Send a message to the App, which will be received by its [App::on_message] method.
-    pub fn send(&self, message: M) {
-        if self
-            .send_event(AppMessage::Message(Box::new(message)))
-            .is_err()
-        {
+    ///
+    /// If the app has sent more messages than polymodo could keep up with, this one is discarded
+    /// instead. Messages that mustn't get lost should be sent with [AppSender::send_or_queue] or
+    /// [AppSender::send_async], and threads that send many messages at once should use
+    /// [AppSender::send_blocking].
+    pub fn send(&self, message: M) -> Result<(), ChannelFullError> {
+        let event = AppEvent {
+            app_key: self.app_key,
+            message: AppMessage::Message(Box::new(message)),
+        };
+
+        match self.sender.try_send(event) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => {
+                tracing::warn!(
+                    app_key = self.app_key,
+                    message = std::any::type_name::<M>(),
+                    "app message channel is full, discarding a message"
+                );
+                Err(ChannelFullError)
+            }
+            Err(TrySendError::Closed(_)) => {
+                log::error!("tried sending message to app, but the message receiver has been dropped: is polymodo dead?");
+                Ok(())
+            }
+        }
+    }
+
+    /// Like [AppSender::send], but wait for room in the channel rather than discarding the
+    /// message. For tasks on the event loop, which can wait without blocking it.
+    pub async fn send_async(&self, message: M) {
+        let event = AppEvent {
+            app_key: self.app_key,
+            message: AppMessage::Message(Box::new(message)),
+        };
+
+        if self.sender.send(event).await.is_err() {
+            log::error!("tried sending message to app, but the message receiver has been dropped: is polymodo dead?");
+        }
+    }
+
+    /// Like [AppSender::send], but if the channel is full, send the message from a task once
+    /// there is room, rather than discarding it. For messages that mustn't get lost, like the
+    /// user's input, sent from the event loop (where [AppSender::send_blocking] would deadlock).
+    ///
+    /// Messages sent this way arrive in the order they were sent, even if some had to wait.
+    pub fn send_or_queue(&self, message: M) {
+        let event = AppEvent {
+            app_key: self.app_key,
+            message: AppMessage::Message(Box::new(message)),
+        };
+
+        let mut queue = self.queue.lock().unwrap();
+        if queue.draining {
+            queue.events.push_back(event);
+            return;
+        }
+
+        match self.sender.try_send(event) {
+            Ok(()) => {}
+            Err(TrySendError::Full(event)) => {
+                queue.events.push_back(event);
+                queue.draining = true;
+
+                let sender = self.sender.clone();
+                let queue = Arc::clone(&self.queue);
+                // detached: it finishes as soon as the queue is empty
+                drop(self.event_loop.spawn(drain_queue(sender, queue)));
+            }
+            Err(TrySendError::Closed(_)) => {
+                log::error!("tried sending message to app, but the message receiver has been dropped: is polymodo dead?");
+            }
+        }
+    }
+
+    /// Send `message` once `delay` has passed without another message being sent this way, e.g.
+    /// to only act on a query once the user stops typing. A previous message that is still
+    /// waiting is discarded.
+    ///
+    /// Each clone of an [AppSender] debounces its messages separately.
+    pub fn send_debounced(&self, message: M, delay: Duration) {
+        let sender = self.clone();
+        let task = self.event_loop.spawn(async move {
+            smol::Timer::after(delay).await;
+            sender.send_async(message).await;
+        });
+
+        // dropping the previous message's task aborts it
+        *self.debounced.lock().unwrap() = Some(AbortOnDrop::new(task));
+    }
+
+    /// Like [AppSender::send], but wait for room in the channel rather than discarding the
+    /// message. This must not be called from the event loop, which is what makes room.
+    pub fn send_blocking(&self, message: M) {
+        let event = AppEvent {
+            app_key: self.app_key,
+            message: AppMessage::Message(Box::new(message)),
+        };
+
+        if self.sender.send_blocking(event).is_err() {
             log::error!("tried sending message to app, but the message receiver has been dropped: is polymodo dead?");
         }
     }
 
     pub fn finish(&self) {
-        self.send_event(AppMessage::Finished)
-            .expect("could not send message to polymodo");
+        if let Err(e) = self.send_control(AppMessage::Finished) {
+            log::error!("couldn't tell polymodo the app finished: {e}");
+        }
+    }
+
+    /// Let the user know the app is (or is no longer) doing work in the background.
+    /// This may be called from any thread.
+    pub fn set_busy(&self, busy: bool) {
+        if let Err(e) = self.send_control(AppMessage::SetBusy(busy)) {
+            log::error!("couldn't set the app's busy state ({e}); is polymodo dead?");
+        }
+    }
+
+    /// Ask polymodo to stop all of its apps (including this one) and quit.
+    pub fn shutdown_polymodo(&self) {
+        if let Err(e) = self.send_control(AppMessage::Shutdown) {
+            log::error!("couldn't ask polymodo to shut down ({e}); is polymodo dead?");
+        }
     }
 }
 
+/// An app sent more messages than polymodo could keep up with. See [AppSender::send].
+#[derive(Debug, derive_more::Display, derive_more::Error)]
+#[display("the app message channel is full")]
+pub struct ChannelFullError;
+
 pub struct AppEvent {
     pub app_key: AppKey,
     pub message: AppMessage,
@@ -189,6 +477,10 @@ pub enum AppMessage {
     Message(Box<dyn std::any::Any + Send>),
     /// App spawned a task and wishes for the runtime to manage it
     SpawnLocal(AbortOnDrop),
+    /// App requests polymodo to stop all apps and quit
+    Shutdown,
+    /// App started or stopped doing work in the background
+    SetBusy(bool),
 }
 
 pub trait Abortable {
@@ -227,9 +519,60 @@ impl Drop for AbortOnDrop {
     }
 }
 
-#[derive(Debug, derive_more::Display, Copy, Clone, PartialEq, Eq, Decode, Encode)]
+/// The pixels of an app's window, see [App::screenshot].
+pub type Screenshot = slint::SharedPixelBuffer<slint::Rgba8Pixel>;
+
+/// Parsed as in the configuration file: `launcher`, `note`, `bookmarks` or `launch-stats`.
+#[derive(
+    Debug, derive_more::Display, Copy, Clone, PartialEq, Eq, Decode, Encode, strum::EnumString,
+)]
+#[strum(serialize_all = "kebab-case")]
 pub enum AppName {
     Launcher,
+    Note,
+    Bookmarks,
+    #[display("launch stats")]
+    LaunchStats,
+    /// See [crate::testing::FakeApp]
+    #[cfg(test)]
+    Fake,
+}
+
+impl AppName {
+    /// The directory an app's configuration is kept in, see [AppExt::read_config]. Unlike how the
+    /// app is displayed, this is lowercase and doesn't contain spaces, as users edit these files.
+    pub fn config_dir_name(self) -> &'static str {
+        match self {
+            AppName::Launcher => "launcher",
+            AppName::Note => "note",
+            AppName::Bookmarks => "bookmarks",
+            AppName::LaunchStats => "launch-stats",
+            #[cfg(test)]
+            AppName::Fake => "fake",
+        }
+    }
+}
+
+/// Options an app is created with, as requested by whoever spawned it.
+#[derive(Debug, Default, Clone, Decode, Encode)]
+pub struct AppOptions {
+    pub select_action: SelectAction,
+    /// Show internal scoring information in the UI, for debugging.
+    pub debug_scores: bool,
+    /// Only offer applications that can open this file, and open it with the selected one.
+    pub file: Option<PathBuf>,
+}
+
+/// What to do with the entry a user selected.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Decode, Encode)]
+pub enum SelectAction {
+    /// Launch the selected entry.
+    #[default]
+    Launch,
+    /// Don't launch anything; return the path of the selected entry's desktop file.
+    PrintPath,
+    /// Don't launch anything; return the selected entry's Exec line.
+    PrintExec,
 }
 
 pub trait AppResult {
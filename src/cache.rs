@@ -0,0 +1,186 @@
+use bincode::{Decode, Encode};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// All caches that registered themselves with [register].
+static REGISTRY: Mutex<Vec<&'static dyn Cache>> = Mutex::new(Vec::new());
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Decode, Encode, derive_more::Display)]
+pub enum CacheKind {
+    /// Desktop entries found on the system
+    #[display("entries")]
+    Entries,
+    /// Icons, decoded and ready to be shown
+    #[display("icons")]
+    Icons,
+}
+
+/// A snapshot of a cache, for `polymodo cache status`.
+#[derive(Debug, Clone, Decode, Encode)]
+pub struct CacheStatus {
+    pub name: String,
+    /// Where the cache is stored on disk, if it isn't only kept in memory
+    pub path: Option<PathBuf>,
+    /// Approximate size, in bytes
+    pub size: u64,
+    pub entries: u64,
+    /// When the cache was last (re)filled, in seconds since the unix epoch
+    pub last_refresh: Option<u64>,
+}
+
+/// A cache the daemon keeps, that can be inspected and cleared through the registry.
+pub trait Cache: Sync {
+    fn kind(&self) -> CacheKind;
+
+    fn status(&self) -> CacheStatus;
+
+    /// Drop everything in this cache, so that it is filled anew the next time it is used.
+    fn clear(&self);
+}
+
+/// Make `cache` available to [status] and [clear]. Registering a kind twice does nothing.
+pub fn register(cache: &'static dyn Cache) {
+    let mut registry = REGISTRY.lock().unwrap();
+
+    if !registry.iter().any(|c| c.kind() == cache.kind()) {
+        registry.push(cache);
+    }
+}
+
+/// The status of every registered cache.
+pub fn status() -> Vec<CacheStatus> {
+    REGISTRY
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|cache| cache.status())
+        .collect()
+}
+
+/// Clear the registered cache of `kind`, or all of them if `None`. Returns which were cleared.
+pub fn clear(kind: Option<CacheKind>) -> Vec<CacheKind> {
+    let registry = REGISTRY.lock().unwrap();
+    let caches = registry
+        .iter()
+        .filter(|cache| kind.is_none_or(|kind| cache.kind() == kind));
+
+    caches
+        .map(|cache| {
+            cache.clear();
+            log::info!("cleared the {} cache", cache.kind());
+
+            cache.kind()
+        })
+        .collect()
+}
+
+/// Clear the registered caches that were last refreshed `max_age` ago or longer, so that stale
+/// data doesn't stick around for as long as the daemon runs. Returns how long until the next of
+/// the others expires, or `None` if none of them are filled.
+pub fn clear_expired(max_age: Duration) -> Option<Duration> {
+    let now = unix_time(SystemTime::now()).unwrap_or_default();
+    let mut next_expiry: Option<u64> = None;
+
+    for cache in REGISTRY.lock().unwrap().iter() {
+        let Some(expires_in) = expires_in(cache.status().last_refresh, now, max_age) else {
+            continue;
+        };
+
+        if expires_in == 0 {
+            cache.clear();
+            log::info!("the {} cache expired, cleared it", cache.kind());
+        } else {
+            next_expiry = Some(next_expiry.map_or(expires_in, |next| next.min(expires_in)));
+        }
+    }
+
+    next_expiry.map(Duration::from_secs)
+}
+
+/// In how many seconds a cache last refreshed at `last_refresh` expires, 0 if it already has, or
+/// `None` if it is empty.
+fn expires_in(last_refresh: Option<u64>, now: u64, max_age: Duration) -> Option<u64> {
+    let age = now.saturating_sub(last_refresh?);
+
+    Some(max_age.as_secs().saturating_sub(age))
+}
+
+/// The status of polymodo's persisted state (launch history, ...).
+///
+/// This isn't a cache that can be cleared, but it is shown alongside them, as stale state looks
+/// a lot like a stale cache.
+pub fn state_status() -> Option<CacheStatus> {
+    let path = crate::persistence::get_polymodo_state_home()?;
+
+    let mut size = 0;
+    let mut entries = 0;
+    let mut last_refresh = None;
+
+    for file in walkdir::WalkDir::new(&path)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+    {
+        let Ok(metadata) = file.metadata() else {
+            continue;
+        };
+
+        size += metadata.len();
+        entries += 1;
+        last_refresh = last_refresh.max(metadata.modified().ok().and_then(unix_time));
+    }
+
+    Some(CacheStatus {
+        name: "state".to_string(),
+        path: Some(path),
+        size,
+        entries,
+        last_refresh,
+    })
+}
+
+/// Seconds since the unix epoch, as stored in [CacheStatus::last_refresh].
+pub fn unix_time(time: SystemTime) -> Option<u64> {
+    time.duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs())
+}
+
+/// Print `statuses` as a table, for humans.
+pub fn print_status(statuses: &[CacheStatus]) {
+    let now = unix_time(SystemTime::now()).unwrap_or_default();
+
+    for status in statuses {
+        let path = status
+            .path
+            .as_ref()
+            .map(|path| path.display().to_string())
+            .unwrap_or_else(|| "(in memory)".to_string());
+        let last_refresh = match status.last_refresh {
+            Some(time) => format!("{}s ago", now.saturating_sub(time)),
+            None => "never".to_string(),
+        };
+
+        println!(
+            "{:<8} {:>6} entries {:>10} bytes  refreshed {last_refresh:<12} {path}",
+            status.name, status.entries, status.size
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn caches_expire_after_max_age() {
+        let max_age = Duration::from_secs(600);
+
+        assert_eq!(expires_in(Some(1000), 1100, max_age), Some(500));
+        assert_eq!(expires_in(Some(1000), 1600, max_age), Some(0));
+        assert_eq!(expires_in(Some(1000), 5000, max_age), Some(0));
+        // refreshed after `now`, e.g. because the clock changed: not expired
+        assert_eq!(expires_in(Some(2000), 1000, max_age), Some(600));
+        // nothing to expire
+        assert_eq!(expires_in(None, 1000, max_age), None);
+    }
+}
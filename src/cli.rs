@@ -8,8 +8,91 @@ pub struct Args {
     /// Do not connect to or launch the polymodo daemon
     #[arg(long)]
     pub standalone: bool,
+    /// Run a single app without a daemon, print its result as JSON to stdout, and exit.
+    /// Exits with a zero exit code whenever the app ran, even if its result is `null`.
+    #[arg(long, conflicts_with_all = ["standalone", "single", "toggle"])]
+    pub once: bool,
     /// If an application of the same type is already running, don't launch it.
     /// This argument does nothing when combined with --standalone, as a standalone instance can't have any apps running already.
     #[arg(long, short)]
     pub single: bool,
+    /// Close the application if it is already open, or open it if it isn't.
+    /// This is what you'll typically want to bind to a key in your compositor.
+    #[arg(long, short, conflicts_with = "single")]
+    pub toggle: bool,
+    /// Don't launch the selected entry; print the path of its desktop file to stdout instead.
+    /// Exits with a non-zero exit code if nothing was selected.
+    #[arg(long)]
+    pub no_exec: bool,
+    /// Like --no-exec, but print the entry's Exec line instead of its path.
+    #[arg(long)]
+    pub print_exec: bool,
+    /// Open the note quick-capture window instead of the launcher.
+    /// Notes are stored in $XDG_DATA_HOME/polymodo/notes.md
+    #[arg(long)]
+    pub note: bool,
+    /// Open a searchable list of your GTK bookmarks instead of the launcher.
+    /// The selected bookmark is opened with xdg-open.
+    #[arg(long, conflicts_with = "note")]
+    pub bookmarks: bool,
+    /// Open a table of the applications you launch most, and how the launcher ranks them.
+    /// Delete forgets the selected application's launch history.
+    #[arg(long, conflicts_with_all = ["note", "bookmarks"])]
+    pub launch_stats: bool,
+    /// Open FILE with the selected application: only applications that can open it are listed,
+    /// the default one first.
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["note", "bookmarks", "launch_stats"])]
+    pub file: Option<std::path::PathBuf>,
+    /// Show the fuzzy score, frecency bias and last launch of each entry.
+    /// This can also be toggled with Ctrl+D.
+    #[arg(long, hide = true, alias = "debug-ranking")]
+    pub debug_scores: bool,
+    /// Keep configuration in DIR, and state in DIR/state, instead of in $XDG_CONFIG_HOME/polymodo
+    /// and $XDG_STATE_HOME/polymodo. Each DIR gets a daemon of its own.
+    #[arg(long, value_name = "DIR", global = true)]
+    pub config: Option<std::path::PathBuf>,
+    /// Don't open a window: print the lines read from stdin that match QUERY, best match first.
+    /// Exits with a non-zero exit code if no line matched.
+    #[arg(long, value_name = "QUERY", conflicts_with_all = ["standalone", "once", "toggle", "stats"])]
+    pub filter: Option<String>,
+    /// Print the score of each match before it, separated by a tab. Higher scores are better.
+    #[arg(long, requires = "filter")]
+    pub print_scores: bool,
+    /// Print how long the daemon has been running and how many apps it is running, then exit.
+    /// Exits with a non-zero exit code if no daemon is running.
+    #[arg(long, conflicts_with_all = ["standalone", "once"])]
+    pub stats: bool,
+    /// Write a PNG screenshot of the running launcher's window (or that of --note, ...) to
+    /// stdout, for debugging.
+    #[arg(long, conflicts_with_all = ["standalone", "once", "toggle", "stats", "filter"])]
+    pub screenshot_surface: bool,
+    #[command(subcommand)]
+    pub command: Option<CliCommand>,
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub enum CliCommand {
+    /// Inspect or clear the caches of the polymodo daemon.
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub enum CacheAction {
+    /// Print the path, size, entry count and last refresh of each cache.
+    Status,
+    /// Clear a cache, so that it is filled anew the next time it is needed.
+    Clear {
+        #[arg(value_enum, default_value_t = CacheSelection::All)]
+        cache: CacheSelection,
+    },
+}
+
+#[derive(clap::ValueEnum, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CacheSelection {
+    Icons,
+    Entries,
+    All,
 }
@@ -0,0 +1,192 @@
+//! Talking to the compositor directly, for what the wayland protocols don't offer: knowing which
+//! window had focus before polymodo's, and giving focus back to it.
+//!
+//! Only Hyprland and Sway are supported; on other compositors, nothing happens.
+
+use anyhow::{bail, Context};
+use serde_json::Value;
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// How long the compositor may take to answer. Focus is recorded and returned off the event loop,
+/// but a compositor that doesn't answer still mustn't hold up returning focus forever.
+const IPC_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// A window of the compositor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Toplevel {
+    /// By its address, like `0x55d3c2a8e9f0`
+    Hyprland(String),
+    /// By its container id
+    Sway(i64),
+}
+
+impl Toplevel {
+    /// The window that has focus, if there is one and the compositor is supported.
+    pub fn active() -> Option<Self> {
+        let active = if let Some(socket) = hyprland_socket() {
+            hyprland_active_window(socket)
+        } else if let Some(socket) = sway_socket() {
+            sway_focused_container(socket)
+        } else {
+            return None;
+        };
+
+        match active {
+            Ok(active) => active,
+            Err(e) => {
+                log::warn!("couldn't ask the compositor for the focused window: {e:#}");
+                None
+            }
+        }
+    }
+
+    /// Give focus to this window, if it still exists.
+    pub fn focus(&self) -> anyhow::Result<()> {
+        match self {
+            Toplevel::Hyprland(address) => {
+                let socket = hyprland_socket().context("not running in Hyprland")?;
+                let reply =
+                    hyprland_request(socket, &format!("dispatch focuswindow address:{address}"))?;
+
+                if reply.trim() != "ok" {
+                    bail!("hyprland refused to focus {address}: {}", reply.trim());
+                }
+            }
+            Toplevel::Sway(id) => {
+                let socket = sway_socket().context("not running in Sway")?;
+                let reply =
+                    sway_request(socket, SWAY_RUN_COMMAND, &format!("[con_id={id}] focus"))?;
+
+                // one result per command: `[{"success": true}]`
+                let succeeded = reply
+                    .as_array()
+                    .is_some_and(|results| results.iter().all(|result| result["success"] == true));
+                if !succeeded {
+                    bail!("sway refused to focus container {id}: {reply}");
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn hyprland_socket() -> Option<PathBuf> {
+    let signature = std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE")?;
+    let runtime_dir = xdg::BaseDirectories::new().runtime_dir?;
+
+    Some(
+        runtime_dir
+            .join("hypr")
+            .join(signature)
+            .join(".socket.sock"),
+    )
+}
+
+fn hyprland_active_window(socket: PathBuf) -> anyhow::Result<Option<Toplevel>> {
+    let reply = hyprland_request(socket, "j/activewindow")?;
+    let window: Value = serde_json::from_str(&reply)?;
+
+    // `{}` if no window has focus
+    Ok(window["address"]
+        .as_str()
+        .map(|address| Toplevel::Hyprland(address.to_string())))
+}
+
+/// Send one request to Hyprland's control socket, which answers it and hangs up.
+fn hyprland_request(socket: PathBuf, request: &str) -> anyhow::Result<String> {
+    let mut stream = connect(socket)?;
+    stream.write_all(request.as_bytes())?;
+
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply)?;
+
+    Ok(reply)
+}
+
+const SWAY_MAGIC: &[u8] = b"i3-ipc";
+const SWAY_RUN_COMMAND: u32 = 0;
+const SWAY_GET_TREE: u32 = 4;
+
+fn sway_socket() -> Option<PathBuf> {
+    std::env::var_os("SWAYSOCK").map(PathBuf::from)
+}
+
+fn sway_focused_container(socket: PathBuf) -> anyhow::Result<Option<Toplevel>> {
+    let tree = sway_request(socket, SWAY_GET_TREE, "")?;
+
+    Ok(focused_node(&tree).map(Toplevel::Sway))
+}
+
+/// The id of the focused node in a sway tree, searched depth-first.
+fn focused_node(node: &Value) -> Option<i64> {
+    if node["focused"] == true {
+        return node["id"].as_i64();
+    }
+
+    ["nodes", "floating_nodes"]
+        .iter()
+        .filter_map(|children| node[children].as_array())
+        .flatten()
+        .find_map(focused_node)
+}
+
+/// Send one message to sway's IPC socket, and read its reply. Both are framed as the magic
+/// string, the payload length, the message type, and the payload.
+fn sway_request(socket: PathBuf, message_type: u32, payload: &str) -> anyhow::Result<Value> {
+    let mut stream = connect(socket)?;
+
+    let mut message = SWAY_MAGIC.to_vec();
+    message.extend((payload.len() as u32).to_ne_bytes());
+    message.extend(message_type.to_ne_bytes());
+    message.extend(payload.as_bytes());
+    stream.write_all(&message)?;
+
+    let mut header = [0; SWAY_MAGIC.len() + 8];
+    stream.read_exact(&mut header)?;
+    if !header.starts_with(SWAY_MAGIC) {
+        bail!("not a sway IPC reply");
+    }
+    let length = u32::from_ne_bytes(header[6..10].try_into().unwrap());
+
+    let mut reply = vec![0; length as usize];
+    stream.read_exact(&mut reply)?;
+
+    Ok(serde_json::from_slice(&reply)?)
+}
+
+fn connect(socket: PathBuf) -> anyhow::Result<UnixStream> {
+    let stream = UnixStream::connect(&socket)
+        .with_context(|| format!("couldn't connect to {}", socket.display()))?;
+    stream.set_read_timeout(Some(IPC_TIMEOUT))?;
+    stream.set_write_timeout(Some(IPC_TIMEOUT))?;
+
+    Ok(stream)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn focused_sway_container_is_found() {
+        let tree = serde_json::json!({
+            "id": 1,
+            "focused": false,
+            "nodes": [{
+                "id": 4,
+                "focused": false,
+                "nodes": [{ "id": 7, "focused": false, "nodes": [] }],
+                "floating_nodes": [{ "id": 9, "focused": true, "nodes": [] }],
+            }],
+        });
+
+        assert_eq!(focused_node(&tree), Some(9));
+
+        let unfocused = serde_json::json!({ "id": 1, "focused": false, "nodes": [] });
+        assert_eq!(focused_node(&unfocused), None);
+    }
+}
@@ -1,2 +1,454 @@
-#[expect(unused)] // This will be used when we actually read out a config.
-pub struct Options {}
+use crate::app::AppName;
+use crate::server::RateLimit;
+use indexmap::IndexMap;
+use ini::Ini;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// How long apps may take to stop, unless configured otherwise.
+const DEFAULT_APP_STOP_TIMEOUT: Duration = Duration::from_secs(5);
+/// How many apps may be kept hidden, unless configured otherwise.
+const DEFAULT_MAX_HIDDEN_APPS: usize = 2;
+
+/// Options read from polymodo's configuration file, see [config_file].
+///
+/// The launcher's own settings aren't among these: it keeps them in a JSON file it can write back
+/// to, see [crate::mode::launch::settings::LauncherSettings]. `[launcher]` only holds what applies
+/// to every window.
+///
+/// ```ini
+/// [daemon]
+/// app_stop_timeout_seconds = 5
+/// max_hidden_apps = 2
+/// idle_timeout_minutes = 0
+/// cache_max_age_minutes = 0
+/// rate_limit_messages = 100
+/// rate_limit_window_seconds = 1
+/// rate_limit_penalty_seconds = 1
+///
+/// [launcher]
+/// anchor = center
+/// return_focus = false
+///
+/// [shortcuts]
+/// launcher = launcher
+///
+/// [launch.env]
+/// MOZ_ENABLE_WAYLAND = 1
+///
+/// [launch.env.org.kde.*]
+/// QT_QPA_PLATFORM = wayland
+/// ```
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// How long an app may take to stop, before the daemon considers it stuck. That is only
+    /// logged, see [crate::polymodo::Polymodo::stop_app].
+    pub app_stop_timeout: Duration,
+    /// How many apps may be kept hidden to open instantly the next time, see
+    /// [crate::app::App::hide]. Each costs the memory of its window.
+    pub max_hidden_apps: usize,
+    /// How long the daemon may sit idle before it exits, see [crate::polymodo::Polymodo::idle_for].
+    /// `None` to keep running.
+    pub idle_timeout: Option<Duration>,
+    /// How long the daemon keeps a cache before it is cleared and filled anew, see
+    /// [crate::cache::clear_expired]. `None` to keep caches until they're cleared by hand.
+    pub cache_max_age: Option<Duration>,
+    /// How many messages a client may send the daemon, before it is throttled.
+    pub rate_limit: RateLimit,
+    /// Where on the screen polymodo's windows are placed.
+    pub anchor: WindowAnchor,
+    /// Whether to explicitly give focus back to the window that had it before an app opened,
+    /// once the app closes, see [crate::compositor].
+    pub return_focus: bool,
+    /// Environment variables to launch applications with.
+    pub launch_env: LaunchEnv,
+    /// Global shortcuts to register with the compositor, by their id, and the app each toggles. See
+    /// [crate::shortcuts].
+    pub shortcuts: IndexMap<String, AppName>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            app_stop_timeout: DEFAULT_APP_STOP_TIMEOUT,
+            max_hidden_apps: DEFAULT_MAX_HIDDEN_APPS,
+            idle_timeout: None,
+            cache_max_age: None,
+            rate_limit: RateLimit::default(),
+            anchor: WindowAnchor::default(),
+            return_focus: false,
+            launch_env: LaunchEnv::default(),
+            shortcuts: IndexMap::new(),
+        }
+    }
+}
+
+/// Where on the screen windows are placed: in the center, or against one or two of its edges.
+///
+/// Never against two opposite edges: the compositor would then decide the window's size in that
+/// direction, while polymodo's windows size themselves.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, strum::EnumString, strum::EnumIter)]
+#[strum(serialize_all = "kebab-case")]
+pub enum WindowAnchor {
+    #[default]
+    Center,
+    Top,
+    Bottom,
+    Left,
+    Right,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl WindowAnchor {
+    /// Whether windows are placed against the top, bottom, left and right edge, respectively.
+    pub fn edges(self) -> [bool; 4] {
+        use WindowAnchor::*;
+
+        [
+            matches!(self, Top | TopLeft | TopRight),
+            matches!(self, Bottom | BottomLeft | BottomRight),
+            matches!(self, Left | TopLeft | BottomLeft),
+            matches!(self, Right | TopRight | BottomRight),
+        ]
+    }
+}
+
+/// Environment variables for launched applications, which the daemon doesn't get from the user's
+/// shell profile.
+///
+/// `[launch.env]` applies to every application, and `[launch.env.<pattern>]` to those whose
+/// desktop file id matches `pattern`, in which `*` matches anything and `?` any one character.
+#[derive(Debug, Clone, Default)]
+pub struct LaunchEnv {
+    global: Vec<(String, String)>,
+    /// In the order they appear in the configuration file
+    per_app: Vec<(String, Vec<(String, String)>)>,
+}
+
+impl LaunchEnv {
+    /// The variables to launch the application with desktop file id `id` with. Where they
+    /// conflict, per-application variables win over global ones, and later sections over earlier
+    /// ones.
+    pub fn for_app(&self, id: &str) -> IndexMap<String, String> {
+        let per_app = self
+            .per_app
+            .iter()
+            .filter(|(pattern, _)| glob_matches(pattern, id))
+            .flat_map(|(_, vars)| vars);
+
+        self.global.iter().chain(per_app).cloned().collect()
+    }
+}
+
+impl Options {
+    /// Read the configuration file. Missing files, and options that can't be read, are replaced
+    /// by their defaults.
+    pub fn load() -> Self {
+        let Some(path) = config_file() else {
+            return Self::default();
+        };
+
+        match Ini::load_from_file(&path) {
+            Ok(ini) => Self::parse(&ini),
+            Err(ini::Error::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => {
+                log::warn!("couldn't read {}: {e}", path.display());
+                Self::default()
+            }
+        }
+    }
+
+    fn parse(ini: &Ini) -> Self {
+        let mut options = Self::default();
+
+        let daemon = ini.section(Some("daemon"));
+        if let Some(timeout) = daemon.and_then(|daemon| daemon.get("app_stop_timeout_seconds")) {
+            match timeout.parse::<u64>() {
+                // every app would be considered stuck
+                Ok(0) => log::warn!("app_stop_timeout_seconds must be at least 1, ignoring it"),
+                Ok(secs) => options.app_stop_timeout = Duration::from_secs(secs),
+                Err(e) => log::warn!("invalid app_stop_timeout_seconds {timeout:?}: {e}"),
+            }
+        }
+        if let Some(max) = daemon.and_then(|daemon| daemon.get("max_hidden_apps")) {
+            match max.parse::<usize>() {
+                Ok(max) => options.max_hidden_apps = max,
+                Err(e) => log::warn!("invalid max_hidden_apps {max:?}: {e}"),
+            }
+        }
+        if let Some(timeout) = daemon.and_then(|daemon| daemon.get("idle_timeout_minutes")) {
+            match timeout.parse::<u64>() {
+                Ok(0) => options.idle_timeout = None,
+                Ok(minutes) => options.idle_timeout = Some(Duration::from_secs(minutes * 60)),
+                Err(e) => log::warn!("invalid idle_timeout_minutes {timeout:?}: {e}"),
+            }
+        }
+        if let Some(max_age) = daemon.and_then(|daemon| daemon.get("cache_max_age_minutes")) {
+            match max_age.parse::<u64>() {
+                Ok(0) => options.cache_max_age = None,
+                Ok(minutes) => options.cache_max_age = Some(Duration::from_secs(minutes * 60)),
+                Err(e) => log::warn!("invalid cache_max_age_minutes {max_age:?}: {e}"),
+            }
+        }
+        if let Some(max) = daemon.and_then(|daemon| daemon.get("rate_limit_messages")) {
+            match max.parse::<u32>() {
+                // every client would be throttled
+                Ok(0) => log::warn!("rate_limit_messages must be at least 1, ignoring it"),
+                Ok(max) => options.rate_limit.max_messages = max,
+                Err(e) => log::warn!("invalid rate_limit_messages {max:?}: {e}"),
+            }
+        }
+        if let Some(window) = daemon.and_then(|daemon| daemon.get("rate_limit_window_seconds")) {
+            match window.parse::<u64>() {
+                Ok(0) => log::warn!("rate_limit_window_seconds must be at least 1, ignoring it"),
+                Ok(secs) => options.rate_limit.window = Duration::from_secs(secs),
+                Err(e) => log::warn!("invalid rate_limit_window_seconds {window:?}: {e}"),
+            }
+        }
+        if let Some(penalty) = daemon.and_then(|daemon| daemon.get("rate_limit_penalty_seconds")) {
+            match penalty.parse::<u64>() {
+                Ok(secs) => options.rate_limit.penalty = Duration::from_secs(secs),
+                Err(e) => log::warn!("invalid rate_limit_penalty_seconds {penalty:?}: {e}"),
+            }
+        }
+
+        let launcher = ini.section(Some("launcher"));
+        if let Some(anchor) = launcher.and_then(|launcher| launcher.get("anchor")) {
+            match anchor.parse::<WindowAnchor>() {
+                Ok(anchor) => options.anchor = anchor,
+                Err(e) => log::warn!("invalid anchor {anchor:?}: {e}"),
+            }
+        }
+        if let Some(return_focus) = launcher.and_then(|launcher| launcher.get("return_focus")) {
+            match return_focus.parse::<bool>() {
+                Ok(return_focus) => options.return_focus = return_focus,
+                Err(e) => log::warn!("invalid return_focus {return_focus:?}: {e}"),
+            }
+        }
+
+        if let Some(shortcuts) = ini.section(Some("shortcuts")) {
+            for (id, app_name) in shortcuts.iter() {
+                match app_name.parse::<AppName>() {
+                    Ok(app_name) => {
+                        options.shortcuts.insert(id.to_string(), app_name);
+                    }
+                    Err(e) => log::warn!("invalid app {app_name:?} for shortcut {id:?}: {e}"),
+                }
+            }
+        }
+
+        for (section, properties) in ini.iter() {
+            let Some(section) = section else {
+                continue;
+            };
+            let vars = || {
+                properties
+                    .iter()
+                    .map(|(key, value)| (key.to_string(), value.to_string()))
+                    .collect::<Vec<_>>()
+            };
+
+            if section == "launch.env" {
+                options.launch_env.global.extend(vars());
+            } else if let Some(pattern) = section.strip_prefix("launch.env.") {
+                options
+                    .launch_env
+                    .per_app
+                    .push((pattern.to_string(), vars()));
+            }
+        }
+
+        options
+    }
+}
+
+/// Whether `text` matches `pattern` entirely, where `*` in the pattern matches any number of
+/// characters, and `?` exactly one.
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+
+    // where to resume after the last `*`, if a later part of the pattern fails to match
+    let mut backtrack = None;
+    let (mut p, mut t) = (0, 0);
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some('?') => (p, t) = (p + 1, t + 1),
+            Some(c) if *c == text[t] => (p, t) = (p + 1, t + 1),
+            // let the last `*` take one more character
+            _ => match backtrack {
+                Some((star, star_t)) => {
+                    backtrack = Some((star, star_t + 1));
+                    (p, t) = (star + 1, star_t + 1);
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// `$XDG_CONFIG_HOME/polymodo/config.ini`, see [crate::persistence::get_polymodo_config_home].
+pub fn config_file() -> Option<PathBuf> {
+    crate::persistence::get_polymodo_config_home().map(|config| config.join("config.ini"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn app_stop_timeout_is_read() {
+        let ini = Ini::load_from_str("[daemon]\napp_stop_timeout_seconds = 12\n").unwrap();
+        assert_eq!(
+            Options::parse(&ini).app_stop_timeout,
+            Duration::from_secs(12)
+        );
+
+        let ini = Ini::load_from_str("[daemon]\napp_stop_timeout_seconds = soon\n").unwrap();
+        assert_eq!(
+            Options::parse(&ini).app_stop_timeout,
+            DEFAULT_APP_STOP_TIMEOUT
+        );
+
+        let ini = Ini::load_from_str("[daemon]\napp_stop_timeout_seconds = 0\n").unwrap();
+        assert_eq!(
+            Options::parse(&ini).app_stop_timeout,
+            DEFAULT_APP_STOP_TIMEOUT
+        );
+    }
+
+    #[test]
+    fn idle_timeout_is_read() {
+        let ini = Ini::load_from_str("[daemon]\nidle_timeout_minutes = 30\n").unwrap();
+        assert_eq!(
+            Options::parse(&ini).idle_timeout,
+            Some(Duration::from_secs(30 * 60))
+        );
+
+        // never exit, as without the option
+        let ini = Ini::load_from_str("[daemon]\nidle_timeout_minutes = 0\n").unwrap();
+        assert_eq!(Options::parse(&ini).idle_timeout, None);
+    }
+
+    #[test]
+    fn rate_limit_is_read() {
+        let ini = Ini::load_from_str(
+            "[daemon]\n\
+            rate_limit_messages = 20\n\
+            rate_limit_window_seconds = 5\n\
+            rate_limit_penalty_seconds = 0\n",
+        )
+        .unwrap();
+        let rate_limit = Options::parse(&ini).rate_limit;
+        assert_eq!(rate_limit.max_messages, 20);
+        assert_eq!(rate_limit.window, Duration::from_secs(5));
+        assert_eq!(rate_limit.penalty, Duration::ZERO);
+
+        // nothing would get through
+        let ini = Ini::load_from_str(
+            "[daemon]\nrate_limit_messages = 0\nrate_limit_window_seconds = 0\n",
+        )
+        .unwrap();
+        let rate_limit = Options::parse(&ini).rate_limit;
+        assert_eq!(rate_limit.max_messages, RateLimit::default().max_messages);
+        assert_eq!(rate_limit.window, RateLimit::default().window);
+    }
+
+    #[test]
+    fn anchor_is_read() {
+        let ini = Ini::load_from_str("[launcher]\nanchor = top-left\n").unwrap();
+        let anchor = Options::parse(&ini).anchor;
+        assert_eq!(anchor, WindowAnchor::TopLeft);
+        assert_eq!(anchor.edges(), [true, false, true, false]);
+
+        let ini = Ini::load_from_str("[launcher]\nanchor = somewhere\n").unwrap();
+        assert_eq!(Options::parse(&ini).anchor, WindowAnchor::Center);
+    }
+
+    #[test]
+    fn shortcuts_are_read() {
+        let ini = Ini::load_from_str(
+            "[shortcuts]\nlauncher = launcher\nstats = launch-stats\nbroken = calculator\n",
+        )
+        .unwrap();
+        let shortcuts = Options::parse(&ini).shortcuts;
+
+        assert_eq!(
+            shortcuts.into_iter().collect::<Vec<_>>(),
+            [
+                ("launcher".to_string(), AppName::Launcher),
+                ("stats".to_string(), AppName::LaunchStats),
+            ]
+        );
+    }
+
+    #[test]
+    fn return_focus_is_read() {
+        let ini = Ini::load_from_str("[launcher]\nreturn_focus = true\n").unwrap();
+        assert!(Options::parse(&ini).return_focus);
+
+        let ini = Ini::load_from_str("[launcher]\nreturn_focus = please\n").unwrap();
+        assert!(!Options::parse(&ini).return_focus);
+    }
+
+    #[test]
+    fn anchors_leave_the_size_to_the_window() {
+        use strum::IntoEnumIterator;
+
+        for anchor in WindowAnchor::iter() {
+            let [top, bottom, left, right] = anchor.edges();
+            assert!(
+                !(top && bottom),
+                "{anchor:?} stretches the window vertically"
+            );
+            assert!(
+                !(left && right),
+                "{anchor:?} stretches the window horizontally"
+            );
+        }
+    }
+
+    #[test]
+    fn launch_env_prefers_specific_sections() {
+        let ini = Ini::load_from_str(
+            "[launch.env]\n\
+             MOZ_ENABLE_WAYLAND = 1\n\
+             QT_QPA_PLATFORM = xcb\n\
+             [launch.env.org.kde.*]\n\
+             QT_QPA_PLATFORM = wayland\n\
+             [launch.env.org.kde.okular.desktop]\n\
+             QT_QPA_PLATFORM = offscreen\n",
+        )
+        .unwrap();
+        let env = Options::parse(&ini).launch_env;
+
+        let okular = env.for_app("org.kde.okular.desktop");
+        assert_eq!(okular["MOZ_ENABLE_WAYLAND"], "1");
+        assert_eq!(okular["QT_QPA_PLATFORM"], "offscreen");
+        assert_eq!(
+            env.for_app("org.kde.dolphin.desktop")["QT_QPA_PLATFORM"],
+            "wayland"
+        );
+        assert_eq!(env.for_app("firefox.desktop")["QT_QPA_PLATFORM"], "xcb");
+    }
+
+    #[test]
+    fn globs_are_matched() {
+        assert!(glob_matches("firefox.desktop", "firefox.desktop"));
+        assert!(glob_matches("org.kde.*", "org.kde.okular.desktop"));
+        assert!(glob_matches("*fire*", "firefox.desktop"));
+        assert!(glob_matches("gimp-?.?.desktop", "gimp-2.0.desktop"));
+        assert!(!glob_matches("org.kde.*", "org.gnome.Nautilus.desktop"));
+        assert!(!glob_matches("firefox", "firefox.desktop"));
+    }
+}
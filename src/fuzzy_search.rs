@@ -11,6 +11,8 @@ pub struct FuzzySearch<const C: usize, D: Sync + Send + 'static> {
     // notified any time a user may read matches and get a new result from it
     notify: crate::notify::Notify,
     query: [String; C],
+    // kept to score matches with, see [FuzzySearch::get_scored_matches]
+    config: nucleo::Config,
 }
 
 pub trait Row<const C: usize> {
@@ -57,6 +59,31 @@ impl<const C: usize, D: Sync + Send + 'static> FuzzySearch<C, D> {
         matched
     }
 
+    /// Like [FuzzySearch::get_matches], but with the score of each match.
+    /// Higher scores are better matches.
+    pub fn get_scored_matches(&self) -> Vec<(u32, &D)> {
+        let snapshot = self.nucleo.snapshot();
+        // nucleo doesn't expose the scores it sorted by, so score the matches again
+        let mut matcher = nucleo::Matcher::new(self.config.clone());
+        let pattern = snapshot.pattern();
+
+        snapshot
+            .matched_items(..)
+            .map(|item| {
+                let score = pattern
+                    .score(item.matcher_columns, &mut matcher)
+                    .unwrap_or_default();
+                (score, item.data)
+            })
+            .collect()
+    }
+
+    /// Block until all pushed entries are matched against the current query.
+    /// Useful without an event loop to wait for notifications on, like in tests.
+    pub fn finish(&mut self) {
+        while self.nucleo.tick(10).running {}
+    }
+
     pub fn tick(&mut self) -> nucleo::Status {
         self.nucleo.tick(0)
     }
@@ -65,6 +92,13 @@ impl<const C: usize, D: Sync + Send + 'static> FuzzySearch<C, D> {
         self.notify.clone()
     }
 
+    /// Remove all entries from the matcher. The current query is kept.
+    pub fn clear(&mut self) {
+        self.nucleo.restart(true);
+        // restarting invalidates all existing injectors
+        self.injector = self.nucleo.injector();
+    }
+
     /// Access the inner nucleo [nucleo::Injector]
     #[inline]
     #[expect(unused)]
@@ -84,7 +118,7 @@ where
         let nucleo = {
             let notify = notify.clone();
             nucleo::Nucleo::new(
-                config,
+                config.clone(),
                 std::sync::Arc::new(move || notify.notify()),
                 None,
                 C as u32,
@@ -97,6 +131,7 @@ where
             injector,
             notify,
             query: [const { String::new() }; _],
+            config,
         }
     }
 
@@ -132,8 +167,41 @@ where
     }
 
     /// Add a bunch of entries to the matcher.
-    #[expect(unused)]
     pub fn push_all(&self, iter: impl IntoIterator<Item = D>) {
         iter.into_iter().for_each(|i| self.push(i))
     }
 }
+
+impl Row<1> for String {
+    type Output = String;
+
+    fn columns(&self) -> [Self::Output; 1] {
+        [self.clone()]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn search_runs_without_an_app() {
+        let mut search: FuzzySearch<1, String> =
+            FuzzySearch::create_with_config(nucleo::Config::DEFAULT);
+        search.push_all(["firefox", "thunderbird", "fire extinguisher"].map(String::from));
+
+        search.search::<0>("fire");
+        search.finish();
+
+        let matches = search.get_scored_matches();
+        let lines = matches
+            .iter()
+            .map(|(_, line)| line.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        assert!(lines.contains(&"firefox") && lines.contains(&"fire extinguisher"));
+        // best match first
+        assert!(matches[0].0 >= matches[1].0);
+        assert!(matches.iter().all(|(score, _)| *score > 0));
+    }
+}
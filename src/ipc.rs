@@ -1,16 +1,33 @@
-use crate::app::AppName;
-use bincode::error::DecodeError;
+use crate::app::{AppKey, AppName, AppOptions};
+use crate::cache::{CacheKind, CacheStatus};
+use bincode::config::{Configuration, Limit, LittleEndian, Varint};
+use bincode::error::{DecodeError, EncodeError};
 use bincode::{Decode, Encode};
 use derive_more::{Display, Error, From};
 use smol::io::{AsyncReadExt, AsyncWriteExt};
 use smol::lock::Mutex;
 use smol::net::unix::{UnixListener, UnixStream};
 use smol::Async;
+use std::collections::VecDeque;
 use std::net::Shutdown;
 use std::os::unix::net::SocketAddr;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-const BINCODE_CONFIG: bincode::config::Configuration = bincode::config::standard();
+/// The maximum size of a single message. A peer exceeding this is disconnected.
+pub const MAX_MESSAGE_SIZE: usize = 1_048_576;
+/// Limited to [MAX_MESSAGE_SIZE], so that a peer claiming a huge string or path can't make us
+/// allocate room for it before it was even sent.
+const BINCODE_CONFIG: Configuration<LittleEndian, Varint, Limit<MAX_MESSAGE_SIZE>> =
+    bincode::config::standard().with_limit::<MAX_MESSAGE_SIZE>();
+
+/// How many bytes `message` takes when sent, to compare against [MAX_MESSAGE_SIZE].
+pub fn encoded_len(message: &impl Encode) -> Result<usize, EncodeError> {
+    let mut writer = bincode::enc::write::SizeWriter::default();
+    bincode::encode_into_writer(message, &mut writer, BINCODE_CONFIG)?;
+
+    Ok(writer.bytes_written)
+}
 
 pub type IpcC2S = IpcClient<ClientboundMessage, ServerboundMessage>;
 pub type IpcS2C = IpcClient<ServerboundMessage, ClientboundMessage>;
@@ -20,29 +37,104 @@ pub enum ServerboundMessage {
     Ping,
     Spawn(AppSpawnOptions),
     Goodbye,
+    // new variants go below, so that the discriminants of existing ones don't change
+    /// Stop the app with this name if it is running, or spawn it if it isn't.
+    Toggle(AppName, AppOptions),
+    /// Stop the app with this key right away, and reply with its result.
+    Stop(AppKey),
+    /// Ask for the diagnostic state of a running app with this name.
+    AppState(AppName),
+    /// Ask for the status of the daemon's caches.
+    CacheStatus,
+    /// Clear the daemon's cache of this kind, or all of them.
+    ClearCache(Option<CacheKind>),
+    /// Ask whether the daemon is healthy, and for how long it has been running.
+    Health,
+    /// Ask for a screenshot of the window of a running app with this name.
+    Screenshot(AppName),
 }
 
 #[derive(Debug, Decode, Encode)]
 pub struct AppSpawnOptions {
     pub app_name: AppName,
     pub single: bool,
+    pub options: AppOptions,
 }
 
 #[derive(Debug, Decode, Encode)]
 pub enum ClientboundMessage {
     Pong,
     AppResult(String), // TODO: apps return much prettier things than String. This could be type-safe, but requires a bit of thought.
+    // new variants go below, so that the discriminants of existing ones don't change
+    /// Reply to [ServerboundMessage::Toggle]. If the app was opened, an `AppResult` follows once it stops.
+    Toggled(ToggleAction),
+    /// The client is sending too many messages; the server will stop reading for a while.
+    RateLimited,
+    /// The server failed to handle the client's request.
+    Error {
+        code: ErrorCode,
+        message: String,
+    },
+    /// Reply to [ServerboundMessage::AppState], as a JSON document.
+    AppState(String),
+    /// Reply to [ServerboundMessage::CacheStatus].
+    CacheStatus(Vec<CacheStatus>),
+    /// Reply to [ServerboundMessage::ClearCache], with the caches that were cleared.
+    CacheCleared(Vec<CacheKind>),
+    /// Reply to [ServerboundMessage::Health].
+    HealthOk {
+        uptime_secs: u64,
+        app_count: u32,
+    },
+    /// Reply to [ServerboundMessage::Screenshot], as a PNG image.
+    Screenshot(Vec<u8>),
+    /// Reply to a `single` [ServerboundMessage::Spawn]: the app is already running, so nothing
+    /// was spawned.
+    AlreadyRunning,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Decode, Encode)]
+pub enum ErrorCode {
+    /// The requested app could not be spawned.
+    SpawnFailed,
+    /// The requested app does not exist (anymore).
+    NoSuchApp,
+    /// The app stopped, but its result could not be retrieved.
+    ResultUnavailable,
+    /// A message could not be encoded, decoded or transferred.
+    Ipc,
+    /// The app's window could not be captured.
+    ScreenshotFailed,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Decode, Encode)]
+pub enum ToggleAction {
+    Opened,
+    Closed,
+}
+
+#[derive(Debug, Error, Display, From)]
+pub enum IpcSendError {
+    EncodeError(EncodeError),
+    IoError(std::io::Error),
 }
 
 #[derive(Debug, Error, Display, From)]
 pub enum IpcReceiveError {
     DecodeError(DecodeError),
     IoError(std::io::Error),
+    #[display("message exceeds the maximum size of {MAX_MESSAGE_SIZE} bytes")]
+    MessageTooLarge,
 }
 
 pub struct IpcClient<In, Out> {
     stream: UnixStream,
-    backlog: Arc<Mutex<Vec<u8>>>,
+    /// The read half: bytes read from the stream that haven't been decoded into a message yet.
+    /// A deque, so that draining decoded messages from the front doesn't shift what's left.
+    backlog: Arc<Mutex<VecDeque<u8>>>,
+    /// The write half. Held for the whole of a write, so that messages sent from clones of this
+    /// client never interleave, and [IpcClient::shutdown] can wait for writes in progress.
+    writer: Arc<Mutex<UnixStream>>,
     addr: SocketAddr,
     marker: std::marker::PhantomData<(In, Out)>,
 }
@@ -50,6 +142,7 @@ pub struct IpcClient<In, Out> {
 impl<A, B> IpcClient<A, B> {
     fn new(stream: UnixStream, addr: SocketAddr) -> Self {
         Self {
+            writer: Arc::new(Mutex::new(stream.clone())),
             stream,
             backlog: Default::default(),
             addr,
@@ -57,12 +150,24 @@ impl<A, B> IpcClient<A, B> {
         }
     }
 
+    /// Wrap a stream that is already connected, e.g. one half of [UnixStream::pair].
+    #[cfg(test)]
+    pub fn from_stream(stream: UnixStream) -> std::io::Result<Self> {
+        let addr = stream.peer_addr()?;
+
+        Ok(Self::new(stream, addr))
+    }
+
     pub fn addr(&self) -> &SocketAddr {
         &self.addr
     }
 
+    /// Stop writing to the stream, after everything sent so far has been written to it.
     pub async fn shutdown(&self) -> std::io::Result<()> {
-        self.stream.shutdown(Shutdown::Write)?;
+        let mut writer = self.writer.lock().await;
+
+        writer.flush().await?;
+        writer.shutdown(Shutdown::Write)?;
 
         Ok(())
     }
@@ -73,11 +178,22 @@ where
     In: bincode::Decode<()>,
     Out: bincode::Encode,
 {
-    pub async fn send(&self, message: Out) -> anyhow::Result<()> {
-        let mut stream = self.stream.clone();
-
+    pub async fn send(&self, message: Out) -> Result<(), IpcSendError> {
         let bytes = bincode::encode_to_vec(message, BINCODE_CONFIG)?;
-        let _ = stream.write(&bytes).await?;
+
+        // a single write may not take all bytes: a message cut short would corrupt every message
+        // after it.
+        let mut writer = self.writer.lock().await;
+        writer.write_all(&bytes).await?;
+        writer.flush().await?;
+
+        if tracing::enabled!(tracing::Level::DEBUG) {
+            tracing::debug!(
+                message_type = std::any::type_name::<Out>(),
+                bytes = bytes.len(),
+                "ipc send"
+            );
+        }
 
         Ok(())
     }
@@ -86,16 +202,32 @@ where
         loop {
             let mut backlog = self.backlog.lock().await;
 
-            match bincode::decode_from_slice(&backlog, BINCODE_CONFIG) {
+            // bincode wants a single slice to decode from; this only moves bytes around if the
+            // deque wrapped around since the last decode.
+            match bincode::decode_from_slice(backlog.make_contiguous(), BINCODE_CONFIG) {
                 Ok((message, bytes)) => {
                     // remove `bytes` bytes from our buffer
                     // as we might have already read bytes of the next message, it's essential that
                     // we keep them around for the next attempt to `recv`!
                     drop(backlog.drain(..bytes));
 
+                    if tracing::enabled!(tracing::Level::DEBUG) {
+                        tracing::debug!(
+                            message_type = std::any::type_name::<In>(),
+                            bytes_consumed = bytes,
+                            "ipc recv"
+                        );
+                    }
+
                     return Ok(message);
                 }
                 Err(DecodeError::UnexpectedEnd { .. }) => {} // just read more!
+                Err(DecodeError::LimitExceeded) => {
+                    backlog.clear();
+                    let _ = self.stream.shutdown(Shutdown::Both);
+
+                    return Err(IpcReceiveError::MessageTooLarge);
+                }
                 Err(e) => return Err(e.into()),
             }
 
@@ -107,7 +239,16 @@ where
                 let err: std::io::Error = std::io::ErrorKind::BrokenPipe.into();
                 return Err(err.into());
             } else {
-                backlog.extend_from_slice(&buf[..read]);
+                backlog.extend(&buf[..read]);
+            }
+
+            if backlog.len() > MAX_MESSAGE_SIZE {
+                // whatever is on the other end is sending us way too much data:
+                // stop reading and close the connection.
+                backlog.clear();
+                let _ = self.stream.shutdown(Shutdown::Both);
+
+                return Err(IpcReceiveError::MessageTooLarge);
             }
         }
     }
@@ -118,6 +259,7 @@ impl<A, B> Clone for IpcClient<A, B> {
         Self {
             stream: self.stream.clone(),
             backlog: Arc::clone(&self.backlog),
+            writer: Arc::clone(&self.writer),
             addr: self.addr.clone(),
             marker: Default::default(),
         }
@@ -139,10 +281,44 @@ impl IpcServer {
     }
 }
 
+/// A client connected to a server-side client, without going through the polymodo socket.
+#[cfg(test)]
+pub fn pair() -> std::io::Result<(IpcC2S, IpcS2C)> {
+    let (client, server) = UnixStream::pair()?;
+
+    Ok((
+        IpcClient::from_stream(client)?,
+        IpcClient::from_stream(server)?,
+    ))
+}
+
+/// What the daemon's socket and lock are named after. Daemons with a different `--config` are
+/// kept apart, so that each only ever sees its own configuration.
+fn daemon_name() -> String {
+    use std::os::unix::ffi::OsStrExt;
+
+    match crate::persistence::config_dir() {
+        // the path itself may well be too long for a socket address
+        Some(dir) => format!("polymodo-{:016x}", fnv1a(dir.as_os_str().as_bytes())),
+        None => "polymodo".to_string(),
+    }
+}
+
+/// The 64-bit FNV-1a hash of `bytes`. Unlike std's hashers, this never changes between Rust
+/// releases: clients and daemons built by different compilers must still find each other.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    bytes.iter().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(PRIME)
+    })
+}
+
 pub fn get_polymodo_socket_addr() -> SocketAddr {
     use std::os::linux::net::SocketAddrExt;
 
-    SocketAddr::from_abstract_name(b"polymodo.sock")
+    SocketAddr::from_abstract_name(format!("{}.sock", daemon_name()))
         .expect("can't construct polymodo socket address. Is abstract namespacing not supported on the version of linux you are running?")
 }
 
@@ -170,6 +346,46 @@ fn bind_listener(addr: SocketAddr) -> std::io::Result<UnixListener> {
     Ok(async_listener.into())
 }
 
+/// Take the lock that only the polymodo daemon holds, for as long as the returned file is open.
+///
+/// Returns `None` if another process holds it: that one is (or is becoming) the daemon. The lock is
+/// released by the kernel when its holder exits, so a crashed daemon never leaves it behind.
+pub fn lock_daemon() -> std::io::Result<Option<std::fs::File>> {
+    let runtime_dir = xdg::BaseDirectories::new()
+        .runtime_dir
+        .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::NotFound))?;
+
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(runtime_dir.join(format!("{}.lock", daemon_name())))?;
+
+    match file.try_lock() {
+        Ok(()) => Ok(Some(file)),
+        Err(std::fs::TryLockError::WouldBlock) => Ok(None),
+        Err(std::fs::TryLockError::Error(e)) => Err(e),
+    }
+}
+
+/// Connect to a polymodo daemon that is still starting up, retrying until `timeout` has passed.
+pub fn wait_for_polymodo_daemon(timeout: Duration) -> std::io::Result<IpcC2S> {
+    let deadline = Instant::now() + timeout;
+
+    loop {
+        match connect_to_polymodo_daemon() {
+            Err(e) if e.kind() == std::io::ErrorKind::ConnectionRefused => {
+                if Instant::now() >= deadline {
+                    return Err(e);
+                }
+
+                std::thread::sleep(Duration::from_millis(20));
+            }
+            result => return result,
+        }
+    }
+}
+
 pub fn connect_to_polymodo_daemon() -> std::io::Result<IpcC2S> {
     let addr = get_polymodo_socket_addr();
     let stream = std::os::unix::net::UnixStream::connect_addr(&addr)?;
@@ -180,3 +396,62 @@ pub fn connect_to_polymodo_daemon() -> std::io::Result<IpcC2S> {
 
     Ok(client)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn existing_discriminants_dont_change() {
+        // clients and daemons of different versions must still understand each other's messages
+        let serverbound = |message: ServerboundMessage| {
+            bincode::encode_to_vec(message, BINCODE_CONFIG).unwrap()[0]
+        };
+        let clientbound = |message: ClientboundMessage| {
+            bincode::encode_to_vec(message, BINCODE_CONFIG).unwrap()[0]
+        };
+
+        assert_eq!(serverbound(ServerboundMessage::Ping), 0);
+        assert_eq!(serverbound(ServerboundMessage::Goodbye), 2);
+        assert_eq!(clientbound(ClientboundMessage::Pong), 0);
+        assert_eq!(clientbound(ClientboundMessage::AppResult(String::new())), 1);
+    }
+
+    #[test]
+    fn forged_lengths_are_refused() {
+        crate::testing::run(async {
+            let (mut forger, stream) = UnixStream::pair().unwrap();
+            let client = IpcC2S::from_stream(stream).unwrap();
+
+            // an `AppResult` whose string claims to be 2^40 bytes long: a u64 length follows 253.
+            let mut forged = vec![1, 253];
+            forged.extend((1u64 << 40).to_le_bytes());
+            forger.write_all(&forged).await.unwrap();
+
+            assert!(matches!(
+                client.recv().await,
+                Err(IpcReceiveError::MessageTooLarge)
+            ));
+        });
+    }
+
+    #[test]
+    fn daemon_names_are_stable() {
+        // the published FNV-1a test vectors
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(fnv1a(b"foobar"), 0x8594_4171_f739_67e8);
+    }
+
+    #[test]
+    fn encoded_len_is_what_is_sent() {
+        let message = ClientboundMessage::Screenshot(vec![7; 1000]);
+
+        assert_eq!(
+            encoded_len(&message).unwrap(),
+            bincode::encode_to_vec(message, BINCODE_CONFIG)
+                .unwrap()
+                .len()
+        );
+    }
+}
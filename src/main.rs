@@ -1,5 +1,7 @@
 pub mod app;
+mod cache;
 mod cli;
+mod compositor;
 mod config;
 mod fuzzy_search;
 mod ipc;
@@ -8,60 +10,116 @@ mod notify;
 mod persistence;
 mod polymodo;
 mod server;
+mod shortcuts;
+#[cfg(test)]
+mod testing;
 mod ui;
 mod xdg;
 
-use crate::cli::Args;
-use crate::ipc::{AppSpawnOptions, ClientboundMessage, IpcC2S, ServerboundMessage};
-use crate::mode::launch::Launcher;
+use crate::cache::CacheKind;
+use crate::cli::{Args, CacheAction, CacheSelection, CliCommand};
+use crate::fuzzy_search::FuzzySearch;
+use crate::ipc::{AppSpawnOptions, ClientboundMessage, IpcC2S, ServerboundMessage, ToggleAction};
 use crate::polymodo::Polymodo;
-use app::AppName;
+use app::{AppName, AppOptions, SelectAction};
 use clap::Parser;
 use slint::winit_030::winit::platform::wayland::{
-    KeyboardInteractivity, Layer, WindowAttributesWayland,
+    Anchor, KeyboardInteractivity, Layer, WindowAttributesWayland,
 };
 use slint::BackendSelector;
-use std::io::ErrorKind;
+use std::io::{ErrorKind, IsTerminal, Write};
+use std::time::Duration;
 use tracing::metadata::LevelFilter;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
 use tracing_subscriber::EnvFilter;
 
+/// How long to wait for a daemon that another polymodo is starting.
+const DAEMON_START_TIMEOUT: Duration = Duration::from_secs(5);
+/// The code polymodo exits with when the daemon failed to run the app.
+const EXIT_DAEMON_ERROR: i32 = 2;
+
 fn main() -> anyhow::Result<()> {
     setup_logging()?;
 
     let args = cli::Args::parse();
+    let expects_selection = args.no_exec || args.print_exec;
+
+    if let Some(dir) = &args.config {
+        // the same directory must always name the same daemon, however it's written
+        std::fs::create_dir_all(dir)?;
+        persistence::set_config_dir(dir.canonicalize()?);
+    }
+
+    if let Some(CliCommand::Cache { action }) = &args.command {
+        return smol::block_on(run_cache_command(action));
+    }
+
+    if args.stats {
+        return smol::block_on(run_stats());
+    }
+
+    if args.screenshot_surface {
+        return smol::block_on(run_screenshot(app_name(&args)));
+    }
+
+    if let Some(query) = &args.filter {
+        let found = run_filter(query, args.print_scores)?;
+        std::process::exit(if found { 0 } else { 1 });
+    }
+
+    if args.once {
+        log::info!("Running a single app");
+
+        // any result, even `null`, means the app ran as it should
+        let result = run_standalone(app_name(&args), app_options(&args))?;
+        println!("{result}");
+
+        return Ok(());
+    }
 
     if args.standalone {
         log::info!("Starting standalone polymodo");
 
-        run_standalone()?;
+        let result = run_standalone(app_name(&args), app_options(&args));
 
-        std::process::exit(0);
+        std::process::exit(print_app_result(result.as_deref().ok(), expects_selection));
     }
 
     // try connecting to a running polymodo daemon.
     match ipc::connect_to_polymodo_daemon() {
         Ok(client) => {
             // ok, we have a client, let's talk with the server!
-            // the client is written in async code, so set up a runtime here.
-
-            match smol::block_on(run_client(args, client)) {
-                Ok(result) => log::info!("finished running, exited with result '{result:?}'"),
-                Err(e) => log::error!("client failed to run: {e}"),
-            };
+            client_main(args, client, expects_selection);
 
             Ok(())
         }
         Err(err) if err.kind() == ErrorKind::ConnectionRefused => {
             // ConnectionRefused happens when there is no one listening on the other end, i.e.
             // there isn't a polymodo daemon yet.
-            // let's become that!
+            // let's become that! unless another polymodo is becoming that right now, that is:
+            // only the one holding the daemon lock may.
+            let _lock = match ipc::lock_daemon() {
+                Ok(Some(lock)) => Some(lock),
+                Ok(None) => {
+                    log::info!("another polymodo daemon is starting, connecting to it instead");
+
+                    let client = ipc::wait_for_polymodo_daemon(DAEMON_START_TIMEOUT)?;
+                    client_main(args, client, expects_selection);
+
+                    return Ok(());
+                }
+                Err(e) => {
+                    log::warn!("couldn't take the daemon lock, starting the daemon anyway: {e}");
+                    None
+                }
+            };
+
             log::info!("Starting polymodo daemon");
 
             server::run_server()?;
 
-            unreachable!();
+            Ok(())
         }
         Err(e) => {
             // errors other than ConnectionRefused are considered fatal, as something other went
@@ -74,54 +132,270 @@ fn main() -> anyhow::Result<()> {
     }
 }
 
+/// Run the client, and exit with the app's result.
+fn client_main(args: Args, client: IpcC2S, expects_selection: bool) {
+    // the client is written in async code, so set up a runtime here.
+    let result = smol::block_on(run_client(args, client));
+
+    std::process::exit(finish_client(result, expects_selection));
+}
+
+/// Print the result of an app the daemon ran, like [print_app_result] does.
+///
+/// Returns the code polymodo should exit with: that of [print_app_result], or
+/// [EXIT_DAEMON_ERROR] if the daemon failed to run the app.
+fn finish_client(result: anyhow::Result<Option<String>>, expects_selection: bool) -> i32 {
+    match result {
+        Ok(result) => {
+            log::info!("finished running, exited with result '{result:?}'");
+
+            print_app_result(result.as_deref(), expects_selection)
+        }
+        Err(e) => {
+            log::error!("client failed to run: {e}");
+
+            EXIT_DAEMON_ERROR
+        }
+    }
+}
+
 /// Run polymodo as a client interacting with the incumbent polymodo daemon.
 ///
 /// This, more or less, just sets up IPC, spawns the desired app, and waits for its result.
 async fn run_client(args: Args, client: IpcC2S) -> anyhow::Result<Option<String>> {
-    client
-        .send(ServerboundMessage::Spawn(AppSpawnOptions {
-            app_name: AppName::Launcher,
+    let app_name = app_name(&args);
+    let options = app_options(&args);
+    let message = if args.toggle {
+        ServerboundMessage::Toggle(app_name, options)
+    } else {
+        ServerboundMessage::Spawn(AppSpawnOptions {
+            app_name,
             single: args.single,
-        }))
-        .await
-        .expect("failed to send");
+            options,
+        })
+    };
+
+    request_app_result(&client, message).await
+}
+
+/// Send `message` to the daemon, and wait for the result of the app it runs, if any.
+async fn request_app_result(
+    client: &IpcC2S,
+    message: ServerboundMessage,
+) -> anyhow::Result<Option<String>> {
+    client.send(message).await?;
+
+    let mut app_result = client.recv().await?;
+
+    if let ClientboundMessage::Toggled(action) = app_result {
+        log::info!("toggle: {action:?}");
+
+        app_result = match action {
+            // the app was opened, so its result will follow.
+            ToggleAction::Opened => client.recv().await?,
+            ToggleAction::Closed => app_result,
+        };
+    }
+
+    client.send(ServerboundMessage::Goodbye).await?;
+    client.shutdown().await?;
+
+    match app_result {
+        ClientboundMessage::AppResult(result) => Ok(Some(result)),
+        ClientboundMessage::Error { code, message } => {
+            Err(anyhow::anyhow!("daemon error ({code:?}): {message}"))
+        }
+        // `--single`: the app that's already running will do the work; nothing was selected here.
+        ClientboundMessage::AlreadyRunning => {
+            log::info!("the app is already running; not spawning another");
+            Ok(None)
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Run `polymodo cache ...`. The in-memory caches live in the daemon, so this talks to it if one is
+/// running; it is never started for this.
+async fn run_cache_command(action: &CacheAction) -> anyhow::Result<()> {
+    let client = match ipc::connect_to_polymodo_daemon() {
+        Ok(client) => Some(client),
+        Err(e) if e.kind() == ErrorKind::ConnectionRefused => None,
+        Err(e) => return Err(e.into()),
+    };
+
+    let reply = match (&client, action) {
+        (Some(client), CacheAction::Status) => {
+            client.send(ServerboundMessage::CacheStatus).await?;
+            Some(client.recv().await?)
+        }
+        (Some(client), CacheAction::Clear { cache }) => {
+            let kind = match cache {
+                CacheSelection::Icons => Some(CacheKind::Icons),
+                CacheSelection::Entries => Some(CacheKind::Entries),
+                CacheSelection::All => None,
+            };
+
+            client.send(ServerboundMessage::ClearCache(kind)).await?;
+            Some(client.recv().await?)
+        }
+        (None, _) => None,
+    };
+
+    if let Some(client) = &client {
+        client.send(ServerboundMessage::Goodbye).await?;
+        client.shutdown().await?;
+    }
+
+    match (action, reply) {
+        (CacheAction::Status, Some(ClientboundMessage::CacheStatus(mut statuses))) => {
+            statuses.extend(cache::state_status());
+            cache::print_status(&statuses);
+        }
+        (CacheAction::Status, None) => {
+            println!("no polymodo daemon is running, so nothing is cached in memory");
+            cache::print_status(&Vec::from_iter(cache::state_status()));
+        }
+        (CacheAction::Clear { .. }, Some(ClientboundMessage::CacheCleared(cleared))) => {
+            for kind in cleared {
+                println!("cleared {kind}");
+            }
+        }
+        (CacheAction::Clear { .. }, None) => {
+            println!("no polymodo daemon is running, so there is nothing to clear");
+        }
+        (_, Some(ClientboundMessage::Error { code, message })) => {
+            anyhow::bail!("daemon error ({code:?}): {message}")
+        }
+        (_, Some(reply)) => anyhow::bail!("unexpected reply from the daemon: {reply:?}"),
+    }
+
+    Ok(())
+}
+
+/// Match the lines of stdin against `query` like the launcher would, and print those that match,
+/// best match first. Returns whether any line matched.
+fn run_filter(query: &str, print_scores: bool) -> anyhow::Result<bool> {
+    let mut search: FuzzySearch<1, String> = FuzzySearch::create_with_config({
+        let mut config = nucleo::Config::DEFAULT;
+        config.prefer_prefix = true;
+        config
+    });
+    for line in std::io::stdin().lines() {
+        search.push(line?);
+    }
+
+    search.search::<0>(query);
+    search.finish();
+
+    let matches = search.get_scored_matches();
+    let mut stdout = std::io::stdout().lock();
+    for (score, line) in &matches {
+        if print_scores {
+            writeln!(stdout, "{score}\t{line}")?;
+        } else {
+            writeln!(stdout, "{line}")?;
+        }
+    }
 
-    let app_result = client.recv().await?;
+    Ok(!matches.is_empty())
+}
+
+/// Run `polymodo --stats`. Like `polymodo cache`, this never starts a daemon.
+async fn run_stats() -> anyhow::Result<()> {
+    let client = match ipc::connect_to_polymodo_daemon() {
+        Ok(client) => client,
+        Err(e) if e.kind() == ErrorKind::ConnectionRefused => {
+            anyhow::bail!("no polymodo daemon is running")
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    client.send(ServerboundMessage::Health).await?;
+    let reply = client.recv().await?;
+
+    client.send(ServerboundMessage::Goodbye).await?;
+    client.shutdown().await?;
+
+    match reply {
+        ClientboundMessage::HealthOk {
+            uptime_secs,
+            app_count,
+        } => {
+            println!("uptime {uptime_secs}s");
+            println!("apps   {app_count}");
+        }
+        ClientboundMessage::Error { code, message } => {
+            anyhow::bail!("daemon error ({code:?}): {message}")
+        }
+        reply => anyhow::bail!("unexpected reply from the daemon: {reply:?}"),
+    }
+
+    Ok(())
+}
+
+async fn run_screenshot(app_name: AppName) -> anyhow::Result<()> {
+    let mut stdout = std::io::stdout();
+    if stdout.is_terminal() {
+        anyhow::bail!("refusing to write a PNG image to a terminal; redirect stdout to a file");
+    }
+
+    let client = match ipc::connect_to_polymodo_daemon() {
+        Ok(client) => client,
+        Err(e) if e.kind() == ErrorKind::ConnectionRefused => {
+            anyhow::bail!("no polymodo daemon is running")
+        }
+        Err(e) => return Err(e.into()),
+    };
 
     client
-        .send(ServerboundMessage::Goodbye)
-        .await
-        .expect("send failed");
-    client.shutdown().await.expect("shutdown failed");
-
-    Ok(match app_result {
-        ClientboundMessage::AppResult(result) => Some(result),
-        _ => None,
-    })
+        .send(ServerboundMessage::Screenshot(app_name))
+        .await?;
+    let reply = client.recv().await?;
+
+    client.send(ServerboundMessage::Goodbye).await?;
+    client.shutdown().await?;
+
+    match reply {
+        ClientboundMessage::Screenshot(png) => {
+            stdout.write_all(&png)?;
+            stdout.flush()?;
+        }
+        ClientboundMessage::Error { code, message } => {
+            anyhow::bail!("daemon error ({code:?}): {message}")
+        }
+        reply => anyhow::bail!("unexpected reply from the daemon: {reply:?}"),
+    }
+
+    Ok(())
 }
 
 /// Run polymodo without connecting to a server and without setting up IPC.
 ///
-/// This function returns when the spawned app dies.
-pub fn run_standalone() -> anyhow::Result<()> {
-    setup_slint_backend();
+/// This function returns the (JSON) result of the spawned app when it dies.
+pub fn run_standalone(app_name: AppName, options: AppOptions) -> anyhow::Result<String> {
+    setup_slint_backend()?;
 
-    slint::invoke_from_event_loop(|| {
+    let (result_sender, result_receiver) = oneshot::channel();
+
+    slint::invoke_from_event_loop(move || {
         let poly = Polymodo::new().into_handle();
         let _run_task = poly.start_running();
-        let app = poly.spawn_app::<Launcher>().expect("Failed to spawn app");
+        let app = mode::spawn_app(&poly, app_name, &options).expect("Failed to spawn app");
 
         slint::spawn_local(async move {
-            let result = poly.wait_for_app_stop(app).await;
-
-            match result {
-                Ok(Some(result)) => {
-                    let result = result.to_json();
-                    log::info!("finished running, exited with result '{result:?}'")
-                }
-                Ok(None) => log::error!("finished running, but could not get app result"),
-                Err(e) => log::error!("finished running with error {e}"),
+            let result = match poly.wait_for_app_stop(app).await {
+                Ok(Some(result)) => result.to_json(),
+                Ok(None) => Err(anyhow::anyhow!(
+                    "finished running, but could not get app result"
+                )),
+                Err(e) => Err(anyhow::anyhow!("finished running with error {e}")),
             };
+            match &result {
+                Ok(result) => log::info!("finished running, exited with result '{result}'"),
+                Err(e) => log::error!("{e}"),
+            }
+
+            let _ = result_sender.send(result);
 
             slint::quit_event_loop().expect("failed to quit");
         })
@@ -131,7 +405,57 @@ pub fn run_standalone() -> anyhow::Result<()> {
 
     slint::run_event_loop_until_quit().expect("slint failed");
 
-    Ok(())
+    result_receiver
+        .try_recv()
+        .map_err(|_| anyhow::anyhow!("the event loop quit before the app stopped"))?
+}
+
+fn app_name(args: &Args) -> AppName {
+    if args.note {
+        AppName::Note
+    } else if args.bookmarks {
+        AppName::Bookmarks
+    } else if args.launch_stats {
+        AppName::LaunchStats
+    } else {
+        AppName::Launcher
+    }
+}
+
+fn app_options(args: &Args) -> AppOptions {
+    let select_action = if args.print_exec {
+        SelectAction::PrintExec
+    } else if args.no_exec {
+        SelectAction::PrintPath
+    } else {
+        SelectAction::Launch
+    };
+
+    AppOptions {
+        select_action,
+        debug_scores: args.debug_scores,
+        // the daemon may have been started from anywhere else
+        file: args
+            .file
+            .as_ref()
+            .map(|file| std::path::absolute(file).unwrap_or_else(|_| file.clone())),
+    }
+}
+
+/// Print the (JSON) result of an app to stdout, if it returned a string.
+///
+/// Returns the code polymodo should exit with: non-zero if a selection was expected, but none was made.
+fn print_app_result(result: Option<&str>, expects_selection: bool) -> i32 {
+    let value = result.and_then(|result| serde_json::from_str(result).ok());
+
+    match value {
+        Some(serde_json::Value::String(output)) => {
+            println!("{output}");
+            0
+        }
+        _ if expects_selection => 1,
+        _ => 0,
+    }
 }
 
 fn setup_logging() -> anyhow::Result<()> {
@@ -148,16 +472,137 @@ fn setup_logging() -> anyhow::Result<()> {
     Ok(())
 }
 
-pub fn setup_slint_backend() {
+/// Select slint's winit backend, which renders with OpenGL onto layer shell surfaces.
+///
+/// Where these surfaces are anchored is configured, see [config::WindowAnchor]. The configuration
+/// is read whenever a window is created, so that changes apply without restarting the daemon.
+///
+/// This fails outside of a Wayland session, or without usable graphics drivers, as in some VMs
+/// and CI: the error says so, rather than panicking.
+pub fn setup_slint_backend() -> anyhow::Result<()> {
     BackendSelector::default()
         .with_winit_window_attributes_hook(|mut attrs| {
+            let anchor = config::Options::load().anchor;
             attrs.platform = Some(Box::new(
                 WindowAttributesWayland::layer_shell()
+                    .with_anchor(layer_anchor(anchor))
                     .with_layer(Layer::Overlay)
                     .with_keyboard_interactivity(KeyboardInteractivity::OnDemand),
             ));
             attrs
         })
         .select()
-        .expect("failed to select");
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "couldn't set up a window system backend: {e}\n\
+                 polymodo needs a Wayland compositor that supports the layer shell protocol \
+                 (is WAYLAND_DISPLAY set?), and OpenGL through EGL. Without a GPU, Mesa's \
+                 software renderer works too: try LIBGL_ALWAYS_SOFTWARE=1."
+            )
+        })
+}
+
+/// The layer shell anchor for `anchor`: no edges at all centers the surface.
+fn layer_anchor(anchor: config::WindowAnchor) -> Anchor {
+    let [top, bottom, left, right] = anchor.edges();
+
+    let mut layer_anchor = Anchor::empty();
+    layer_anchor.set(Anchor::TOP, top);
+    layer_anchor.set(Anchor::BOTTOM, bottom);
+    layer_anchor.set(Anchor::LEFT, left);
+    layer_anchor.set(Anchor::RIGHT, right);
+    layer_anchor
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::server::{serve_client, RateLimit};
+    use crate::testing;
+
+    /// Spawn the fake app on a daemon like `polymodo --no-exec` would, and finish it with
+    /// `output`, or dismiss it if `None`. Returns the code polymodo would exit with.
+    fn exit_code_of_fake_app(output: Option<&str>) -> i32 {
+        let output = output.map(str::to_string);
+
+        let result = testing::run(async move {
+            let poly = testing::polymodo();
+            let _run_task = poly.start_running();
+            let (client, server) = ipc::pair().unwrap();
+
+            let serve = serve_client(poly.clone(), server, RateLimit::default());
+            let message = ServerboundMessage::Spawn(AppSpawnOptions {
+                app_name: AppName::Fake,
+                single: false,
+                options: AppOptions::default(),
+            });
+            let finish_app = async {
+                let key = testing::wait_for_app(&poly, AppName::Fake).await;
+
+                match output {
+                    Some(output) => poly.app_sender::<String>(key).send(output).unwrap(),
+                    None => poly.finish_app(key),
+                }
+            };
+
+            let ((), (result, ())) = smol::future::zip(
+                serve,
+                smol::future::zip(request_app_result(&client, message), finish_app),
+            )
+            .await;
+
+            result
+        });
+
+        finish_client(result, true)
+    }
+
+    #[test]
+    fn selection_exits_zero() {
+        assert_eq!(exit_code_of_fake_app(Some("firefox.desktop")), 0);
+    }
+
+    #[test]
+    fn dismissal_exits_one() {
+        assert_eq!(exit_code_of_fake_app(None), 1);
+    }
+
+    #[test]
+    fn single_spawn_of_running_app_is_not_a_daemon_error() {
+        let result = testing::run(async {
+            let poly = testing::polymodo();
+            let (client, server) = ipc::pair().unwrap();
+
+            poly.spawn_app::<testing::FakeApp>(&AppOptions::default())
+                .unwrap();
+
+            let serve = serve_client(poly, server, RateLimit::default());
+            let message = ServerboundMessage::Spawn(AppSpawnOptions {
+                app_name: AppName::Fake,
+                single: true,
+                options: AppOptions::default(),
+            });
+            let request = request_app_result(&client, message);
+
+            smol::future::zip(serve, request).await.1
+        });
+
+        assert_eq!(finish_client(result, true), 1);
+    }
+
+    #[test]
+    fn daemon_error_exits_two() {
+        let result = testing::run(async {
+            let poly = testing::polymodo();
+            let (client, server) = ipc::pair().unwrap();
+
+            // there is no app with this key to stop
+            let serve = serve_client(poly, server, RateLimit::default());
+            let request = request_app_result(&client, ServerboundMessage::Stop(u32::MAX));
+
+            smol::future::zip(serve, request).await.1
+        });
+
+        assert_eq!(finish_client(result, true), EXIT_DAEMON_ERROR);
+    }
 }
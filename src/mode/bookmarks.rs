@@ -0,0 +1,475 @@
+use crate::app::{App, AppName, AppOptions, AppSender, JsonAppResult, Screenshot};
+use crate::fuzzy_search::FuzzySearch;
+use crate::mode::{spawn_detached, window_title, HideOnDrop, HideOnDropExt};
+use crate::ui;
+use crate::ui::index_model::IndexModel;
+use indexmap::IndexSet;
+use nix::sys::inotify::{AddWatchFlags, InitFlags, Inotify};
+use slint::{ComponentHandle, ModelExt, ModelRc};
+use std::path::PathBuf;
+use std::process::Command;
+use std::rc::Rc;
+
+type BookmarksModel = Rc<IndexModel<usize, Bookmark>>;
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    QuerySet(String),
+    /// Open the bookmark at this index, and close.
+    Open(usize),
+    /// The bookmarks file changed on disk.
+    Reload,
+    SearchUpdated,
+}
+
+/// Fuzzy-searchable list of the user's GTK bookmarks, opened with `xdg-open`.
+pub struct Bookmarks {
+    bookmarks: BookmarksModel,
+    window: HideOnDrop<ui::BookmarksWindow>,
+    sender: AppSender<Message>,
+    search: FuzzySearch<1, SearchEntry>,
+    searching: bool,
+    /// The URI of the bookmark that was opened, if any
+    opened: Option<String>,
+}
+
+impl App for Bookmarks {
+    type Message = Message;
+    type Output = JsonAppResult<Option<String>>;
+
+    const NAME: AppName = AppName::Bookmarks;
+
+    fn create(message_sender: AppSender<Self::Message>, _options: &AppOptions) -> Self {
+        let window: HideOnDrop<ui::BookmarksWindow> =
+            ui::BookmarksWindow::new().unwrap().hide_on_drop();
+        window.set_window_title(window_title(Self::NAME, None));
+
+        let model: BookmarksModel = Default::default();
+
+        // best matches first; the order of the bookmarks file otherwise.
+        window.set_bookmarks(ModelRc::new(
+            model
+                .clone()
+                .filter(|bookmark| bookmark.shown)
+                .sort_by(|a, b| (b.score, a.id).cmp(&(a.score, b.id)))
+                .map(|bookmark| bookmark.to_slint()),
+        ));
+
+        let search: FuzzySearch<1, SearchEntry> =
+            FuzzySearch::create_with_config(nucleo::Config::DEFAULT);
+
+        {
+            let notify = search.notify();
+            let sender = message_sender.clone();
+            message_sender.spawn(async move {
+                loop {
+                    notify.acquire().await;
+
+                    sender.send_async(Message::SearchUpdated).await;
+                }
+            });
+        }
+
+        watch_bookmarks_file(&message_sender);
+
+        {
+            let message_sender = message_sender.clone();
+            window.on_query_edited(move |query| {
+                message_sender.send_or_queue(Message::QuerySet(query.as_str().to_string()));
+            });
+        }
+
+        {
+            let message_sender = message_sender.clone();
+            window.on_escape_pressed(move || {
+                message_sender.finish();
+            });
+        }
+
+        {
+            let message_sender = message_sender.clone();
+            window.on_open(move |id| {
+                if id < 0 {
+                    return;
+                }
+
+                message_sender.send_or_queue(Message::Open(id as usize));
+            });
+        }
+
+        window.show().unwrap();
+
+        let mut bookmarks = Bookmarks {
+            bookmarks: model,
+            window,
+            sender: message_sender,
+            search,
+            searching: false,
+            opened: None,
+        };
+
+        bookmarks.load();
+
+        bookmarks
+    }
+
+    fn on_message(&mut self, message: Self::Message) {
+        match message {
+            Message::QuerySet(query) => {
+                self.searching = !query.is_empty();
+                self.search.search::<0>(query);
+            }
+            Message::Open(id) => {
+                let Some(bookmark) = self.bookmarks.get_value_of_key(&id) else {
+                    return;
+                };
+
+                if let Err(e) = open(&bookmark.uri) {
+                    log::error!("couldn't open {}: {e}", bookmark.uri);
+                    return;
+                }
+
+                self.opened = Some(bookmark.uri);
+                self.sender.finish();
+            }
+            Message::Reload => {
+                log::debug!("bookmarks file changed, reloading");
+                self.load();
+            }
+            Message::SearchUpdated => {
+                self.search.tick();
+
+                let ranked: IndexSet<usize> = self
+                    .search
+                    .get_matches()
+                    .into_iter()
+                    .map(|entry| entry.id)
+                    .collect();
+                let searching = self.searching;
+
+                // the best match is now on top
+                self.window.set_current_bookmark(0);
+
+                self.bookmarks.mutate_all(|_, id, bookmark| {
+                    let position = ranked.get_index_of(id).map(|pos| ranked.len() - pos);
+                    bookmark.shown = position.is_some();
+                    // without a query, every bookmark matches equally well; keep the order of
+                    // the file instead.
+                    bookmark.score = if searching {
+                        position.unwrap_or_default()
+                    } else {
+                        0
+                    };
+                });
+            }
+        }
+    }
+
+    fn screenshot(&self) -> Option<Screenshot> {
+        self.window.window().take_snapshot().ok()
+    }
+
+    fn stop(self) -> Self::Output {
+        JsonAppResult(self.opened)
+    }
+}
+
+impl Bookmarks {
+    /// (Re)read the bookmarks file, replacing all bookmarks.
+    fn load(&mut self) {
+        let content = bookmarks_file()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .unwrap_or_default();
+
+        self.bookmarks.clear();
+        self.search.clear();
+
+        for (id, line) in content.lines().enumerate() {
+            let Some((uri, name)) = parse_bookmark(line) else {
+                continue;
+            };
+
+            self.search.push(SearchEntry {
+                id,
+                name: name.clone(),
+            });
+            self.bookmarks.insert(
+                id,
+                Bookmark {
+                    id,
+                    shown: !self.searching,
+                    score: 0,
+                    name,
+                    location: display_location(uri),
+                    uri: uri.to_string(),
+                },
+            );
+        }
+
+        // rank the new bookmarks against the current query
+        self.sender.send_or_queue(Message::SearchUpdated);
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Bookmark {
+    /// The line of the bookmarks file this bookmark is on
+    id: usize,
+    /// Whether this bookmark should be shown in the UI
+    shown: bool,
+    /// The score this bookmark got from the fuzzy matcher
+    score: usize,
+    name: String,
+    /// Where the bookmark points to, for humans
+    location: String,
+    uri: String,
+}
+
+impl Bookmark {
+    fn to_slint(&self) -> ui::BookmarkEntry {
+        ui::BookmarkEntry {
+            name: self.name.as_str().into(),
+            location: self.location.as_str().into(),
+            id: self.id as i32,
+        }
+    }
+}
+
+struct SearchEntry {
+    id: usize,
+    name: String,
+}
+
+impl crate::fuzzy_search::Row<1> for SearchEntry {
+    type Output = String;
+
+    fn columns(&self) -> [Self::Output; 1] {
+        [self.name.clone()]
+    }
+}
+
+/// `$XDG_CONFIG_HOME/gtk-3.0/bookmarks`
+fn bookmarks_file() -> Option<PathBuf> {
+    let xdg = xdg::BaseDirectories::new();
+
+    xdg.config_home
+        .map(|config| config.join("gtk-3.0").join("bookmarks"))
+}
+
+/// Send [Message::Reload] whenever the bookmarks file is written, replaced, or removed.
+fn watch_bookmarks_file(sender: &AppSender<Message>) {
+    let Some(file) = bookmarks_file() else {
+        return;
+    };
+    let (Some(dir), Some(file_name)) = (file.parent(), file.file_name()) else {
+        return;
+    };
+    let file_name = file_name.to_owned();
+
+    // GTK replaces the file rather than writing to it, so the directory is watched instead.
+    let inotify =
+        Inotify::init(InitFlags::IN_NONBLOCK | InitFlags::IN_CLOEXEC).and_then(|inotify| {
+            inotify.add_watch(
+                dir,
+                AddWatchFlags::IN_CLOSE_WRITE
+                    | AddWatchFlags::IN_MOVED_TO
+                    | AddWatchFlags::IN_CREATE
+                    | AddWatchFlags::IN_DELETE,
+            )?;
+
+            Ok(inotify)
+        });
+
+    let inotify = match inotify
+        .map_err(std::io::Error::from)
+        .and_then(smol::Async::new)
+    {
+        Ok(inotify) => inotify,
+        Err(e) => {
+            log::warn!(
+                "couldn't watch {} for changes, bookmarks won't update live: {e}",
+                dir.display()
+            );
+            return;
+        }
+    };
+
+    let sender_ = sender.clone();
+    sender.spawn(async move {
+        loop {
+            let events = match inotify
+                .read_with(|inotify| inotify.read_events().map_err(std::io::Error::from))
+                .await
+            {
+                Ok(events) => events,
+                Err(e) => {
+                    log::error!("stopped watching bookmarks: {e}");
+                    return;
+                }
+            };
+
+            if events
+                .iter()
+                .any(|event| event.name.as_deref() == Some(file_name.as_os_str()))
+            {
+                sender_.send_async(Message::Reload).await;
+            }
+        }
+    });
+}
+
+/// Parse a line of the bookmarks file into its URI and display name.
+///
+/// Lines are a URI, optionally followed by a space and a label. Without a label, the last
+/// component of the URI's path is used.
+fn parse_bookmark(line: &str) -> Option<(&str, String)> {
+    let line = line.trim();
+    if line.is_empty() {
+        return None;
+    }
+
+    let (uri, label) = match line.split_once(' ') {
+        Some((uri, label)) => (uri, Some(label.trim())),
+        None => (line, None),
+    };
+
+    let name = match label {
+        Some(label) if !label.is_empty() => label.to_string(),
+        _ => {
+            let path = uri_path(uri);
+            let name = path.trim_end_matches('/').rsplit('/').next().unwrap_or("");
+
+            if name.is_empty() {
+                // the root of something: show the whole thing rather than nothing.
+                display_location(uri)
+            } else {
+                percent_decode(name)
+            }
+        }
+    };
+
+    Some((uri, name))
+}
+
+/// The path part of a URI: everything after `scheme://host`.
+fn uri_path(uri: &str) -> &str {
+    let Some((_scheme, rest)) = uri.split_once("://") else {
+        return uri;
+    };
+
+    rest.find('/').map(|idx| &rest[idx..]).unwrap_or("/")
+}
+
+/// Local files are shown as a path; anything else (like `sftp://`) as the whole URI.
+fn display_location(uri: &str) -> String {
+    match uri.strip_prefix("file://") {
+        Some(_) => percent_decode(uri_path(uri)),
+        None => percent_decode(uri),
+    }
+}
+
+/// Decode `%XX` escapes, as found in URIs.
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+
+    let mut idx = 0;
+    while idx < bytes.len() {
+        let escaped = (bytes[idx] == b'%')
+            .then(|| s.get(idx + 1..idx + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                idx += 3;
+            }
+            None => {
+                decoded.push(bytes[idx]);
+                idx += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Open `uri` with the user's preferred application, usually their file manager.
+fn open(uri: &str) -> std::io::Result<()> {
+    // xdg-open exits as soon as it has handed the URI off
+    let pid = spawn_detached(Command::new("xdg-open").arg(uri))?;
+
+    log::info!("Opening {uri} with pid {pid}");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn bookmarks_are_parsed() {
+        let content = "file:///home/user/Documents\n\
+            file:///home/user/My%20Projects Projects\n\
+            sftp://example.com/srv/www  \n\
+            \n\
+            file:/// root\n\
+            smb://nas/\n";
+
+        let bookmarks: Vec<_> = content.lines().filter_map(parse_bookmark).collect();
+        assert_eq!(
+            bookmarks,
+            vec![
+                ("file:///home/user/Documents", "Documents".to_string()),
+                ("file:///home/user/My%20Projects", "Projects".to_string()),
+                ("sftp://example.com/srv/www", "www".to_string()),
+                ("file:///", "root".to_string()),
+                // nothing to name it after but the whole thing
+                ("smb://nas/", "smb://nas/".to_string()),
+            ]
+        );
+
+        // without a label, the last component is decoded
+        assert_eq!(
+            parse_bookmark("file:///home/user/My%20Projects/"),
+            Some((
+                "file:///home/user/My%20Projects/",
+                "My Projects".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn uri_paths_are_found() {
+        assert_eq!(uri_path("file:///home/user"), "/home/user");
+        assert_eq!(uri_path("sftp://user@example.com/srv/www"), "/srv/www");
+        assert_eq!(uri_path("smb://nas"), "/");
+        // not a URI with an authority: taken as a path already
+        assert_eq!(uri_path("/home/user"), "/home/user");
+    }
+
+    #[test]
+    fn locations_are_shown_for_humans() {
+        assert_eq!(
+            display_location("file:///home/user/My%20Projects"),
+            "/home/user/My Projects"
+        );
+        assert_eq!(
+            display_location("sftp://example.com/srv/caf%C3%A9"),
+            "sftp://example.com/srv/café"
+        );
+    }
+
+    #[test]
+    fn percent_escapes_are_decoded() {
+        assert_eq!(percent_decode("My%20Projects"), "My Projects");
+        assert_eq!(percent_decode("caf%c3%a9"), "café");
+        // anything that isn't an escape is kept as is
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz%2"), "%zz%2");
+        // invalid UTF-8 doesn't fail the whole name
+        assert_eq!(percent_decode("a%FFb"), "a\u{FFFD}b");
+    }
+}
@@ -0,0 +1,25 @@
+/// Built-in commands acting on polymodo itself, which may be searched for and run from the launcher.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, strum::EnumIter)]
+pub enum LauncherCommand {
+    ReloadSettings,
+    ClearHistory,
+    QuitDaemon,
+}
+
+impl LauncherCommand {
+    pub fn name(self) -> &'static str {
+        match self {
+            LauncherCommand::ReloadSettings => "Reload settings",
+            LauncherCommand::ClearHistory => "Clear history",
+            LauncherCommand::QuitDaemon => "Quit daemon",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            LauncherCommand::ReloadSettings => "Read the launcher's settings from disk again",
+            LauncherCommand::ClearHistory => "Forget which entries were launched, and how often",
+            LauncherCommand::QuitDaemon => "Close all windows and stop the polymodo daemon",
+        }
+    }
+}
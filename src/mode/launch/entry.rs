@@ -1,59 +1,507 @@
+use super::launcher::split_env_prefix;
 use super::*;
 use crate::app::AppSender;
-use once_map::OnceMap;
+use crate::cache::{unix_time, Cache, CacheKind, CacheStatus};
+use crate::notify::Notify;
 use slint::{Rgba8Pixel, SharedString};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader};
 use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
 use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::{Arc, LazyLock, Mutex};
-use std::time::Instant;
+use std::sync::{Arc, LazyLock, Mutex, Once, RwLock};
+use std::time::{Duration, Instant, SystemTime};
 
 type IconPath = String;
 pub type Pixels = slint::SharedPixelBuffer<Rgba8Pixel>;
+/// The frames of an animated icon, each with how long it is shown for.
+pub type IconFrames = Arc<[(Pixels, Duration)]>;
 
-static DESKTOP_ENTRIES: Mutex<Vec<Arc<DesktopEntry>>> = Mutex::new(Vec::new());
+static DESKTOP_ENTRIES: LazyLock<DesktopEntryCache> = LazyLock::new(Default::default);
 
 static ICONS: LazyLock<icon::Icons> = LazyLock::new(icon::Icons::new);
 
-// contains a None entry if we tried loading the icon, but failed
-static ICONS_RENDERED: LazyLock<OnceMap<IconPath, Box<RenderedIcon>>> = LazyLock::new(OnceMap::new);
+/// The icon theme the user chose for polymodo, if any. See [set_icon_theme].
+static ICON_THEME: Mutex<Option<String>> = Mutex::new(None);
+
+/// The icon theme the desktop uses, which is followed if the user didn't choose one for polymodo.
+/// See [system_icon_theme_changes].
+static SYSTEM_ICON_THEME: Mutex<Option<String>> = Mutex::new(None);
+
+/// Notified when the desktop's icon theme changes the icons polymodo shows, one per subscriber.
+static SYSTEM_ICON_THEME_SUBSCRIBERS: Mutex<Vec<Notify>> = Mutex::new(Vec::new());
+
+/// The size icons are looked up in, and animated icons are scaled to, in pixels.
+const ICON_SIZE: u32 = 32;
+
+/// Frames of animated icons that ask to be shown for less than this are shown for
+/// [DEFAULT_FRAME_DELAY] instead, as browsers do.
+const MIN_FRAME_DELAY: Duration = Duration::from_millis(20);
+const DEFAULT_FRAME_DELAY: Duration = Duration::from_millis(100);
+
+/// The icon theme used when neither the user nor the desktop chose one.
+const DEFAULT_ICON_THEME: &str = "Adwaita";
+
+static ICONS_RENDERED: LazyLock<IconCache> = LazyLock::new(Default::default);
 
 // This is just Option, but with variants named for their meaning.
+#[derive(Clone)]
 enum RenderedIcon {
     Ok(Pixels),
+    /// An animated icon, see [icon_animation]
+    Animated(IconFrames),
     Failed,
 }
 
+/// Desktop entries found by [scour_desktop_entries], so the next launcher can show them right away.
+#[derive(Default)]
+struct DesktopEntryCache {
+    /// A search replaces this snapshot as a whole once it is done, rather than adding to it, so
+    /// that the lock is only ever held for as long as it takes to clone or swap an [Arc].
+    /// Nobody waits for a search to finish.
+    entries: RwLock<Arc<Vec<Arc<DesktopEntry>>>>,
+    /// When the last search for desktop entries finished
+    refreshed: Mutex<Option<SystemTime>>,
+}
+
+impl DesktopEntryCache {
+    fn snapshot(&self) -> Arc<Vec<Arc<DesktopEntry>>> {
+        self.entries.read().unwrap().clone()
+    }
+
+    fn replace(&self, entries: Vec<Arc<DesktopEntry>>) {
+        *self.entries.write().unwrap() = Arc::new(entries);
+    }
+}
+
+impl Cache for DesktopEntryCache {
+    fn kind(&self) -> CacheKind {
+        CacheKind::Entries
+    }
+
+    fn status(&self) -> CacheStatus {
+        let entries = self.snapshot();
+        let size = entries
+            .iter()
+            .map(|entry| {
+                size_of::<DesktopEntry>()
+                    + entry.name.len()
+                    + entry.exec.len()
+                    + entry.path.as_os_str().len()
+            })
+            .sum::<usize>();
+
+        CacheStatus {
+            name: self.kind().to_string(),
+            path: None,
+            size: size as u64,
+            entries: entries.len() as u64,
+            last_refresh: self.refreshed.lock().unwrap().and_then(unix_time),
+        }
+    }
+
+    fn clear(&self) {
+        self.replace(Vec::new());
+        *self.refreshed.lock().unwrap() = None;
+    }
+}
+
+/// Icons rendered by [load_icon], by the `Icon` of the desktop entry they belong to.
+#[derive(Default)]
+struct IconCache {
+    /// contains a [RenderedIcon::Failed] entry if we tried loading the icon, but failed
+    icons: Mutex<HashMap<IconPath, RenderedIcon>>,
+    /// When the last icon was added
+    refreshed: Mutex<Option<SystemTime>>,
+}
+
+impl IconCache {
+    fn get(&self, icon: &str) -> Option<RenderedIcon> {
+        self.icons.lock().unwrap().get(icon).cloned()
+    }
+
+    fn insert(&self, icon: IconPath, rendered: RenderedIcon) {
+        self.icons.lock().unwrap().insert(icon, rendered);
+        *self.refreshed.lock().unwrap() = Some(SystemTime::now());
+    }
+}
+
+impl Cache for IconCache {
+    fn kind(&self) -> CacheKind {
+        CacheKind::Icons
+    }
+
+    fn status(&self) -> CacheStatus {
+        let icons = self.icons.lock().unwrap();
+        let size = icons
+            .values()
+            .map(|icon| match icon {
+                RenderedIcon::Ok(pixels) => pixels.as_bytes().len(),
+                RenderedIcon::Animated(frames) => frames
+                    .iter()
+                    .map(|(pixels, _)| pixels.as_bytes().len())
+                    .sum(),
+                RenderedIcon::Failed => 0,
+            })
+            .sum::<usize>();
+
+        CacheStatus {
+            name: self.kind().to_string(),
+            path: None,
+            size: size as u64,
+            entries: icons.len() as u64,
+            last_refresh: self.refreshed.lock().unwrap().and_then(unix_time),
+        }
+    }
+
+    fn clear(&self) {
+        self.icons.lock().unwrap().clear();
+        *self.refreshed.lock().unwrap() = None;
+    }
+}
+
+/// Look icons up in `theme`, or in the desktop's icon theme if `None` or not installed.
+///
+/// Returns whether the theme changed: if so, the icons rendered from the previous theme have been
+/// dropped, and should be loaded again.
+pub fn set_icon_theme(theme: Option<String>) -> bool {
+    let theme = theme.filter(|theme| {
+        let exists = icon_theme_exists(theme);
+        if !exists {
+            log::warn!("icon theme {theme:?} is not installed, using the default one");
+        }
+
+        exists
+    });
+
+    let mut current = ICON_THEME.lock().unwrap();
+    if *current == theme {
+        return false;
+    }
+
+    *current = theme;
+    drop(current);
+
+    log::info!("using icon theme {}", icon_theme());
+    ICONS_RENDERED.clear();
+
+    true
+}
+
+fn icon_theme() -> String {
+    let system_theme = || SYSTEM_ICON_THEME.lock().unwrap().clone();
+
+    ICON_THEME
+        .lock()
+        .unwrap()
+        .clone()
+        .or_else(system_theme)
+        .unwrap_or_else(|| DEFAULT_ICON_THEME.to_string())
+}
+
+/// Subscribe to changes of the desktop's icon theme: the returned [Notify] is notified whenever
+/// the icons rendered from the previous one have been dropped, and should be loaded again.
+///
+/// Changes are only noticed if they're made through GSettings (as GNOME, and the settings apps of
+/// most GTK desktops do) while a [SystemIconThemeWatch] lives, and are ignored while the user
+/// chose an icon theme for polymodo.
+pub fn system_icon_theme_changes() -> Notify {
+    let notify = Notify::new();
+    SYSTEM_ICON_THEME_SUBSCRIBERS
+        .lock()
+        .unwrap()
+        .push(notify.clone());
+
+    static READ: Once = Once::new();
+    READ.call_once(|| {
+        // gsettings takes a moment to answer: icons are loaded from the default theme in the
+        // meantime, and again if the desktop uses another one.
+        let _ = std::thread::spawn(|| update_system_icon_theme(read_system_icon_theme()));
+    });
+
+    notify
+}
+
+/// The icon theme the desktop uses, according to GSettings.
+fn read_system_icon_theme() -> Option<String> {
+    let output = Command::new("gsettings")
+        .args(["get", "org.gnome.desktop.interface", "icon-theme"])
+        .stderr(Stdio::null())
+        .output()
+        .inspect_err(|e| log::debug!("couldn't read the system icon theme: {e}"))
+        .ok()?;
+
+    parse_gsettings_string(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Follows changes of the desktop's icon theme through `gsettings monitor`, which is killed once
+/// this is dropped.
+pub struct SystemIconThemeWatch(Option<Child>);
+
+impl Drop for SystemIconThemeWatch {
+    fn drop(&mut self) {
+        if let Some(child) = &mut self.0 {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+/// Start following changes of the desktop's icon theme, see [system_icon_theme_changes].
+pub fn watch_system_icon_theme() -> SystemIconThemeWatch {
+    let child = Command::new("gsettings")
+        .args(["monitor", "org.gnome.desktop.interface", "icon-theme"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) => {
+            log::debug!("not watching the system icon theme: {e}");
+            return SystemIconThemeWatch(None);
+        }
+    };
+
+    let stdout = child.stdout.take().expect("stdout is piped");
+    // runs until gsettings exits, or is killed
+    let _ = std::thread::spawn(move || {
+        // lines look like `icon-theme: 'Adwaita'`
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            let theme = line
+                .split_once(':')
+                .and_then(|(_, value)| parse_gsettings_string(value));
+
+            update_system_icon_theme(theme);
+        }
+    });
+
+    SystemIconThemeWatch(Some(child))
+}
+
+/// Use `theme` as the desktop's icon theme, and tell subscribers if that changes the icons that
+/// are shown.
+fn update_system_icon_theme(theme: Option<String>) {
+    if set_system_icon_theme(theme) {
+        let mut subscribers = SYSTEM_ICON_THEME_SUBSCRIBERS.lock().unwrap();
+        // subscribers that went away have dropped their end
+        subscribers.retain(Notify::has_listeners);
+        subscribers.iter().for_each(Notify::notify);
+    }
+}
+
+/// Returns whether this changes the icons that are shown, see [set_icon_theme].
+fn set_system_icon_theme(theme: Option<String>) -> bool {
+    let mut current = SYSTEM_ICON_THEME.lock().unwrap();
+    if *current == theme {
+        return false;
+    }
+
+    *current = theme;
+    drop(current);
+
+    if ICON_THEME.lock().unwrap().is_some() {
+        // the user's choice stands
+        return false;
+    }
+
+    log::info!(
+        "the system icon theme changed, using icon theme {}",
+        icon_theme()
+    );
+    ICONS_RENDERED.clear();
+
+    true
+}
+
+/// Parse a GSettings string value, like `'Adwaita'`. Empty strings are `None`.
+fn parse_gsettings_string(value: &str) -> Option<String> {
+    let value = value.trim().trim_matches('\'');
+
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+/// Whether an icon theme named `theme` is installed, in any of the directories icon themes are
+/// looked for in.
+fn icon_theme_exists(theme: &str) -> bool {
+    icon_dirs().iter().any(|dir| dir.join(theme).is_dir())
+}
+
+/// The directories icon themes are looked for in, most important first.
+fn icon_dirs() -> Vec<PathBuf> {
+    let base_dirs = xdg::BaseDirectories::new();
+
+    let mut icon_dirs = base_dirs.data_dirs;
+    if let Some(data_home) = base_dirs.data_home {
+        icon_dirs.insert(0, data_home);
+    }
+    for dir in &mut icon_dirs {
+        dir.push("icons");
+    }
+    // the legacy location, which some themes are still installed to
+    if let Some(home) = std::env::var_os("HOME") {
+        icon_dirs.push(PathBuf::from(home).join(".icons"));
+    }
+
+    icon_dirs
+}
+
+/// Find `icon` at `size` in `theme`, or, if it doesn't have it, in the themes it inherits from.
+fn find_icon(icon: &str, size: u32, theme: &str) -> Option<PathBuf> {
+    theme_chain(theme)
+        .iter()
+        .find_map(|theme| ICONS.find_icon(icon, size, 1, theme))
+        .map(|icon| icon.path.clone())
+}
+
+/// `theme`, followed by the themes it inherits from, as read from the `Inherits` key of their
+/// `index.theme`, and finally `hicolor`, which every theme inherits from.
+fn theme_chain(theme: &str) -> Arc<[String]> {
+    // index.theme files are read once per theme, rather than once per icon
+    static CHAINS: LazyLock<Mutex<HashMap<String, Arc<[String]>>>> =
+        LazyLock::new(Default::default);
+
+    if let Some(chain) = CHAINS.lock().unwrap().get(theme) {
+        return chain.clone();
+    }
+
+    let chain: Arc<[String]> = inheritance_chain(theme, read_theme_parents).into();
+    CHAINS
+        .lock()
+        .unwrap()
+        .insert(theme.to_string(), chain.clone());
+
+    chain
+}
+
+/// Walk the themes `theme` inherits from depth-first, in the order they're listed, visiting each
+/// theme once. `hicolor` always comes last.
+fn inheritance_chain(theme: &str, parents_of: impl Fn(&str) -> Vec<String>) -> Vec<String> {
+    const FALLBACK_THEME: &str = "hicolor";
+
+    let mut chain = Vec::new();
+    let mut to_visit = vec![theme.to_string()];
+    while let Some(theme) = to_visit.pop() {
+        if theme == FALLBACK_THEME || chain.contains(&theme) {
+            continue;
+        }
+
+        // visited before the themes after it, so pushed in reverse
+        to_visit.extend(parents_of(&theme).into_iter().rev());
+        chain.push(theme);
+    }
+    chain.push(FALLBACK_THEME.to_string());
+
+    chain
+}
+
+/// The themes `theme` inherits from, according to the first `index.theme` of it that is found.
+fn read_theme_parents(theme: &str) -> Vec<String> {
+    let Some(index) = icon_dirs()
+        .into_iter()
+        .map(|dir| dir.join(theme).join("index.theme"))
+        .find(|index| index.is_file())
+    else {
+        return Vec::new();
+    };
+
+    let ini = match ini::Ini::load_from_file(&index) {
+        Ok(ini) => ini,
+        Err(e) => {
+            log::warn!("couldn't read {}: {e}", index.display());
+            return Vec::new();
+        }
+    };
+
+    ini.get_from(Some("Icon Theme"), "Inherits")
+        .map(|inherits| {
+            inherits
+                .split(',')
+                .map(str::trim)
+                .filter(|parent| !parent.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Make the launcher's caches known to [crate::cache], so they can be inspected and cleared.
+pub fn register_caches() {
+    crate::cache::register(&*DESKTOP_ENTRIES);
+    crate::cache::register(&*ICONS_RENDERED);
+}
+
 #[derive(Debug, Clone)]
 pub struct DesktopEntry {
     pub name: SharedString,
     pub generic_name: Option<SharedString>,
     pub description: Option<SharedString>,
     pub path: PathBuf,
+    /// See [crate::xdg::DesktopEntry::id]
+    pub id: String,
+    /// See [crate::xdg::DesktopEntry::overrides]
+    pub overrides: Option<PathBuf>,
     pub exec: String,
     pub icon: Option<String>,
+    pub env: Vec<(String, String)>,
+    /// See [crate::xdg::DesktopEntry::startup_wm_class]
+    pub startup_wm_class: Option<String>,
+    /// See [crate::xdg::DesktopEntry::categories]
+    pub categories: Vec<String>,
+    /// See [crate::xdg::DesktopEntry::keywords]
+    pub keywords: Vec<String>,
+    /// See [crate::xdg::DesktopEntry::mime_types]
+    pub mime_types: Vec<String>,
+}
+
+impl DesktopEntry {
+    /// The file name of the program this entry executes, see [exec_basename].
+    pub fn exec_basename(&self) -> Option<&str> {
+        exec_basename(&self.exec)
+    }
+
+    /// The Exec line, for humans: without field codes like `%u`, which are only meaningful to
+    /// the launcher.
+    pub fn display_exec(&self) -> String {
+        self.exec
+            .split_whitespace()
+            .filter(|arg| !(arg.len() == 2 && arg.starts_with('%')))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// The file name of the program `exec` executes, e.g. `firefox` for `/usr/bin/firefox %u`.
+///
+/// A leading `env FOO=1` only sets up the environment, so it's the program after that.
+fn exec_basename(exec: &str) -> Option<&str> {
+    let mut args = exec.split_whitespace().collect::<Vec<_>>();
+    split_env_prefix(&mut args);
+    let program = *args.first()?;
+
+    program.rsplit('/').next().filter(|name| !name.is_empty())
 }
 
-fn next_id() -> EntryId {
+pub fn next_id() -> EntryId {
     static IDX: AtomicUsize = AtomicUsize::new(0);
     let idx = IDX.fetch_add(1, Ordering::Relaxed);
     EntryId(idx)
 }
 
 pub fn scour_desktop_entries(sender: AppSender<Message>) {
+    sender.set_busy(true);
+
     // immediately push cached entries
-    {
-        let rows = DESKTOP_ENTRIES.lock().unwrap();
-        for row in &*rows {
-            sender.send(Message::NewEntry(next_id(), row.clone()));
-        }
+    let cached = DESKTOP_ENTRIES.snapshot();
+    for row in &*cached {
+        sender.send_blocking(Message::NewEntry(next_id(), row.clone()));
     }
 
     // then start a search for new ones
     let start = Instant::now();
     let entries = crate::xdg::find_desktop_entries();
-    // and add any new ones to the searcher
+    // and add any new ones to the searcher, and to a new snapshot of the cache
     {
-        let mut rows = DESKTOP_ENTRIES.lock().unwrap();
+        let mut rows = Vec::clone(&cached);
         let mut new_entries = 0u32;
 
         for entry in entries {
@@ -66,8 +514,9 @@ pub fn scour_desktop_entries(sender: AppSender<Message>) {
                 continue;
             }
 
-            // if, for this desktop entry, there exists no SearchRow yet (with comparison being done on the source path)
-            if !rows.iter().any(|row| entry.source_path == row.path) {
+            // if, for this desktop entry, there exists no SearchRow yet (with comparison being done
+            // on the desktop file id, so that an entry and the user's override of it don't both show)
+            if !rows.iter().any(|row| entry.id == row.id) {
                 log::trace!("new entry {}", entry.source_path.to_string_lossy(),);
                 new_entries += 1;
 
@@ -77,8 +526,15 @@ pub fn scour_desktop_entries(sender: AppSender<Message>) {
                     generic_name: entry.generic_name.clone().map(Into::into),
                     description: entry.comment.clone().map(Into::into),
                     path: entry.source_path,
+                    id: entry.id,
+                    overrides: entry.overrides,
                     exec,
                     icon: entry.icon,
+                    env: entry.env,
+                    startup_wm_class: entry.startup_wm_class,
+                    categories: entry.categories,
+                    keywords: entry.keywords,
+                    mime_types: entry.mime_types,
                 });
 
                 // let bonus_score = history.get(&launcher_entry.path).cloned().unwrap_or(0);
@@ -87,7 +543,7 @@ pub fn scour_desktop_entries(sender: AppSender<Message>) {
 
                 // and also add it to the fuzzy searcher
                 let entry = rows.last().unwrap().clone();
-                sender.send(Message::NewEntry(next_id(), entry));
+                sender.send_blocking(Message::NewEntry(next_id(), entry));
             }
         }
 
@@ -96,18 +552,36 @@ pub fn scour_desktop_entries(sender: AppSender<Message>) {
 
             log::debug!("Took {time_it_took:?} to find {new_entries} new entries");
         }
+
+        DESKTOP_ENTRIES.replace(rows);
     }
+    *DESKTOP_ENTRIES.refreshed.lock().unwrap() = Some(SystemTime::now());
+
+    sender.set_busy(false);
 }
 
 pub fn is_icon_cached(icon: &str) -> bool {
-    ICONS_RENDERED.get(icon).is_some()
+    ICONS_RENDERED.icons.lock().unwrap().contains_key(icon)
+}
+
+/// Load an icon at a larger size than [load_icon] does, bypassing the cache. This function blocks
+/// on I/O.
+pub fn load_large_icon(icon: &str) -> Option<Pixels> {
+    let path = if icon.starts_with('/') {
+        PathBuf::from(icon)
+    } else {
+        find_icon(icon, 128, &icon_theme())?
+    };
+
+    slint::Image::load_from_path(&path).ok()?.to_rgba8()
 }
 
 /// Try loading an icon, given its path. This function blocks on I/O.
 pub fn load_icon(icon: &str) -> Option<Pixels> {
     if let Some(cached) = ICONS_RENDERED.get(icon) {
         return match cached {
-            RenderedIcon::Ok(pixels) => Some(pixels.clone()),
+            RenderedIcon::Ok(pixels) => Some(pixels),
+            RenderedIcon::Animated(frames) => Some(frames[0].0.clone()),
             RenderedIcon::Failed => None,
         };
     }
@@ -117,30 +591,190 @@ pub fn load_icon(icon: &str) -> Option<Pixels> {
         icon.to_string()
     } else {
         let icon_string = icon.to_string();
-        let icon = ICONS.find_icon(icon_string.as_str(), 32, 1, "Adwaita"); // TODO: find user icon theme
+        let icon = find_icon(icon_string.as_str(), ICON_SIZE, &icon_theme());
 
-        if let Some(icon) = icon {
-            let path = icon.path.to_string_lossy().to_string();
+        if let Some(path) = icon {
+            let path = path.to_string_lossy().to_string();
 
             path
         } else {
             // insert a failed entry into the cache,
             // so that any successive fetches for this icon immediately fail
-            ICONS_RENDERED.insert(icon_string, |_| Box::new(RenderedIcon::Failed));
+            ICONS_RENDERED.insert(icon_string, RenderedIcon::Failed);
             return None;
         }
     };
 
     let icon = icon.to_string();
+    // slint only decodes the first frame of a GIF
+    if path.to_ascii_lowercase().ends_with(".gif") {
+        match load_gif_frames(&path) {
+            Ok(frames) if frames.len() > 1 => {
+                let frames = IconFrames::from(frames);
+                ICONS_RENDERED.insert(icon, RenderedIcon::Animated(frames.clone()));
+
+                return Some(frames[0].0.clone());
+            }
+            Ok(_) => {}
+            Err(e) => log::warn!("couldn't decode {path}: {e}"),
+        }
+    }
+
     if let Ok(image) = slint::Image::load_from_path(path.as_str().as_ref()) {
         let buffer = image.to_rgba8().unwrap(); // TODO: unwrap?
 
-        ICONS_RENDERED.insert(icon, |_| Box::new(RenderedIcon::Ok(buffer.clone())));
+        ICONS_RENDERED.insert(icon, RenderedIcon::Ok(buffer.clone()));
 
         Some(buffer)
     } else {
-        ICONS_RENDERED.insert(icon, |_| Box::new(RenderedIcon::Failed));
+        ICONS_RENDERED.insert(icon, RenderedIcon::Failed);
 
         None
     }
 }
+
+/// The frames of `icon`, if it is animated and was loaded by [load_icon].
+pub fn icon_animation(icon: &str) -> Option<IconFrames> {
+    match ICONS_RENDERED.get(icon)? {
+        RenderedIcon::Animated(frames) => Some(frames),
+        RenderedIcon::Ok(_) | RenderedIcon::Failed => None,
+    }
+}
+
+/// Decode every frame of the GIF at `path`, scaled to fit [ICON_SIZE]. This function blocks on
+/// I/O.
+fn load_gif_frames(path: &str) -> anyhow::Result<Vec<(Pixels, Duration)>> {
+    let file = BufReader::new(std::fs::File::open(path)?);
+
+    decode_gif_frames(file)
+}
+
+fn decode_gif_frames(
+    gif: impl std::io::BufRead + std::io::Seek,
+) -> anyhow::Result<Vec<(Pixels, Duration)>> {
+    use image::AnimationDecoder;
+
+    // every frame is as large as the whole animation, so a large GIF would take as much memory
+    // as its size times its frames: scale them down before keeping them.
+    let frames = image::codecs::gif::GifDecoder::new(gif)?
+        .into_frames()
+        .collect_frames()?;
+
+    let frames = frames
+        .into_iter()
+        .map(|frame| {
+            let delay = Duration::from(frame.delay());
+            let delay = if delay < MIN_FRAME_DELAY {
+                DEFAULT_FRAME_DELAY
+            } else {
+                delay
+            };
+
+            let mut buffer = frame.into_buffer();
+            if buffer.width().max(buffer.height()) != ICON_SIZE {
+                buffer = image::DynamicImage::from(buffer)
+                    .thumbnail(ICON_SIZE, ICON_SIZE)
+                    .into_rgba8();
+            }
+
+            let mut pixels = Pixels::new(buffer.width(), buffer.height());
+            pixels.make_mut_bytes().copy_from_slice(buffer.as_raw());
+
+            (pixels, delay)
+        })
+        .collect();
+
+    Ok(frames)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn exec_basenames_skip_env() {
+        assert_eq!(exec_basename("/usr/bin/firefox %u"), Some("firefox"));
+        assert_eq!(exec_basename("gimp"), Some("gimp"));
+        assert_eq!(
+            exec_basename("env GDK_BACKEND=x11 LANG=C /opt/gimp/bin/gimp %U"),
+            Some("gimp")
+        );
+        // an `env` that isn't only setting variables is what runs
+        assert_eq!(exec_basename("env -u LANG gimp"), Some("env"));
+        assert_eq!(exec_basename(""), None);
+        assert_eq!(exec_basename("/usr/bin/"), None);
+    }
+
+    #[test]
+    fn gsettings_strings_are_parsed() {
+        assert_eq!(
+            parse_gsettings_string("'Adwaita'\n"),
+            Some("Adwaita".to_string())
+        );
+        // as found after the key in `gsettings monitor` lines
+        assert_eq!(
+            parse_gsettings_string(" 'Papirus-Dark'"),
+            Some("Papirus-Dark".to_string())
+        );
+        assert_eq!(parse_gsettings_string("''\n"), None);
+        assert_eq!(parse_gsettings_string(""), None);
+    }
+
+    #[test]
+    fn parent_themes_are_followed() {
+        let parents = |theme: &str| match theme {
+            "Papirus-Dark" => vec!["Papirus".to_string(), "breeze-dark".to_string()],
+            "Papirus" => vec!["breeze".to_string(), "hicolor".to_string()],
+            "breeze-dark" => vec!["breeze".to_string()],
+            // a theme inheriting from itself mustn't be followed forever
+            "breeze" => vec!["breeze".to_string()],
+            _ => vec![],
+        };
+
+        assert_eq!(
+            inheritance_chain("Papirus-Dark", parents),
+            [
+                "Papirus-Dark",
+                "Papirus",
+                "breeze",
+                "breeze-dark",
+                "hicolor"
+            ]
+        );
+        assert_eq!(inheritance_chain("hicolor", parents), ["hicolor"]);
+    }
+
+    #[test]
+    fn gif_frames_are_scaled_and_timed() {
+        use image::codecs::gif::GifEncoder;
+        use image::{Delay, Frame, Rgba, RgbaImage};
+
+        let mut gif = Vec::new();
+        {
+            let mut encoder = GifEncoder::new(&mut gif);
+            let frame = |color, delay_ms| {
+                Frame::from_parts(
+                    RgbaImage::from_pixel(64, 48, Rgba(color)),
+                    0,
+                    0,
+                    Delay::from_numer_denom_ms(delay_ms, 1),
+                )
+            };
+
+            encoder.encode_frame(frame([255, 0, 0, 255], 0)).unwrap();
+            encoder.encode_frame(frame([0, 0, 255, 255], 500)).unwrap();
+        }
+
+        let frames = decode_gif_frames(std::io::Cursor::new(gif)).unwrap();
+        let sizes: Vec<_> = frames
+            .iter()
+            .map(|(pixels, _)| (pixels.width(), pixels.height()))
+            .collect();
+        let delays: Vec<_> = frames.iter().map(|(_, delay)| *delay).collect();
+
+        assert_eq!(sizes, [(32, 24), (32, 24)]);
+        assert_eq!(delays, [DEFAULT_FRAME_DELAY, Duration::from_millis(500)]);
+        // the colour of each frame survives being scaled
+        assert_eq!(&frames[1].0.as_bytes()[..4], &[0, 0, 255, 255]);
+    }
+}
@@ -58,6 +58,27 @@ impl LaunchHistory {
         stat.launch_score + recency_bonus
     }
 
+    /// When this entry was last launched, if it has been.
+    pub fn last_launched(&self, entry: &Path) -> Option<SystemTime> {
+        self.inner.get(entry).map(|stat| stat.last_launched)
+    }
+
+    /// The amount of entries that have launch statistics.
+    pub fn entry_count(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// The entries that have launch statistics, in no particular order. See [LaunchHistory::score]
+    /// and [LaunchHistory::last_launched] for their statistics.
+    pub fn entries(&self) -> impl Iterator<Item = &Path> {
+        self.inner.keys().map(PathBuf::as_path)
+    }
+
+    /// Drop the statistics of this entry, as if it was never launched.
+    pub fn forget(&mut self, entry: &Path) {
+        self.inner.remove(entry);
+    }
+
     pub fn increment_and_decay(&mut self, entry: PathBuf) {
         self.increment(entry);
         self.decay_all();
@@ -80,3 +101,24 @@ impl LaunchHistory {
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn forgotten_entries_are_never_launched() {
+        let firefox = PathBuf::from("/usr/share/applications/firefox.desktop");
+        let foot = PathBuf::from("/usr/share/applications/foot.desktop");
+
+        let mut history = LaunchHistory::default();
+        history.increment(firefox.clone());
+        history.increment(foot.clone());
+
+        history.forget(&firefox);
+
+        assert_eq!(history.score(&firefox), 0.0);
+        assert_eq!(history.last_launched(&firefox), None);
+        assert_eq!(history.entries().collect::<Vec<_>>(), [foot.as_path()]);
+    }
+}
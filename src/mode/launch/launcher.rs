@@ -1,19 +1,36 @@
+use super::command::LauncherCommand;
 use super::entry::*;
 use super::history::LaunchHistory;
+use super::man::*;
 use super::settings::*;
-use crate::app::{App, AppExt, AppName, AppSender, JsonAppResult};
+use crate::app::{
+    App, AppExt, AppName, AppOptions, AppSender, JsonAppResult, Screenshot, SelectAction,
+};
 use crate::fuzzy_search::FuzzySearch;
-use crate::mode::{HideOnDrop, HideOnDropExt};
+use crate::mode::query_history::{LastQuery, QueryRecall};
+use crate::mode::{spawn_detached, window_title, HideOnDrop, HideOnDropExt};
 use crate::ui;
 use crate::ui::index_model::IndexModel;
+use crate::xdg::mime;
 use anyhow::anyhow;
-use slint::{ComponentHandle, ModelExt, ModelRc, SharedString};
+use indexmap::IndexMap;
+use slint::winit_030::winit::event::WindowEvent;
+use slint::winit_030::winit::event_loop::AsyncRequestSerial;
+use slint::winit_030::winit::platform::startup_notify::WindowExtStartupNotify;
+use slint::winit_030::{WinitWindowAccessor, WinitWindowEventResult};
+use slint::{ComponentHandle, Model, ModelExt, ModelRc, SharedString};
+use smol::lock::Semaphore;
+use std::cell::{Cell, RefCell};
 use std::cmp::Ordering;
-use std::io::Write;
+use std::collections::{HashMap, HashSet};
+use std::io::{PipeReader, Read, Write};
 use std::os::unix::prelude::CommandExt;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::rc::Rc;
 use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use strum::IntoEnumIterator;
 
 pub(super) type LauncherEntriesModel = Rc<IndexModel<EntryId, LauncherEntry>>;
 
@@ -21,10 +38,86 @@ pub(super) type LauncherEntriesModel = Rc<IndexModel<EntryId, LauncherEntry>>;
 pub enum Message {
     QuerySet(String),
     Launch(EntryId),
+    /// Launch an application through pkexec.
+    LaunchElevated(EntryId),
     NewEntry(EntryId, Arc<DesktopEntry>),
-    UpdateIcon(EntryId, Pixels),
+    NewManPage(EntryId, Arc<ManPage>),
+    /// An icon finished loading in the background (or failed to).
+    UpdateIcon(EntryId, Option<Pixels>),
+    /// Show this frame of an entry's animated icon.
+    NextIconFrame(EntryId, usize),
     TransparencySet(f32),
     SearchUpdated,
+    /// Hide the entry if it is shown, or unhide it if it's hidden.
+    ToggleHidden(EntryId),
+    /// Show hidden entries anyways (so that they may be unhidden).
+    ShowHiddenSet(bool),
+    ShowCommandsSet(bool),
+    MatchExecSet(bool),
+    WheelMovesSelectionSet(bool),
+    ExecWeightSet(f32),
+    /// Takes effect the next time the launcher opens.
+    MaxIconLoadsSet(u32),
+    AnimationDurationSet(u32),
+    RestoreQuerySecsSet(u32),
+    SearchDebounceSet(u32),
+    MaxQueryLenSet(u32),
+    PreferShorterNamesSet(bool),
+    HoverSelectsSet(bool),
+    HighlightColorSet(String),
+    /// An empty color resets it to the theme's default.
+    HighlightTextColorSet(String),
+    SingleClickLaunchesSet(bool),
+    ShowPreviewSet(bool),
+    ShowIconsSet(bool),
+    PrimaryLabelSet(PrimaryLabel),
+    KeepWarmSet(bool),
+    ApplyExecEnvSet(bool),
+    /// An empty theme resets it to the default one.
+    IconThemeSet(String),
+    /// The desktop's icon theme changed, and with it the icons to show.
+    SystemIconThemeChanged,
+    /// This entry was selected: show its details in the preview.
+    Preview(EntryId),
+    /// Replace the query with the previous (if `true`) or next one from the query history.
+    RecallQuery(bool),
+    /// Replace the query with the most recent one from the query history that matches this one,
+    /// see [QueryRecall::search].
+    SearchQueryHistory(String),
+    /// Complete the query to the name of this entry.
+    Complete(EntryId),
+    /// Show the details of this entry.
+    ShowInfo(EntryId),
+    /// Open the context menu of this entry.
+    ContextMenu(EntryId),
+    /// Copy the path of this entry's desktop file to the clipboard.
+    CopyPath(EntryId),
+    /// The compositor handed out an activation token, see [Launcher::request_activation_token].
+    ActivationTokenDone(AsyncRequestSerial, String),
+    /// The compositor didn't hand out the requested activation token in time.
+    ActivationTokenTimedOut(AsyncRequestSerial),
+    /// The launched application is running.
+    Launched,
+    /// The application could not be launched, for this reason.
+    LaunchFailed(String),
+    /// Show this error to the user, for this long.
+    ShowError(String, Duration),
+    /// This error has been shown long enough.
+    ErrorExpired(String),
+    /// Close the launcher, like pressing escape does.
+    Close,
+    /// The MIME type of the file to open was found out (if it could be), along with the
+    /// application that opens it by default, see [Launcher::find_file_type].
+    FileTypeFound(PathBuf, Option<String>, Option<String>),
+    /// The large icon of an entry finished loading in the background, to be shown here.
+    LargeIconLoaded(LargeIconFor, EntryId, Pixels),
+}
+
+/// Where a large icon is shown, see [Launcher::load_large_icon_for].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum LargeIconFor {
+    Preview,
+    Info,
 }
 
 pub struct Launcher {
@@ -32,51 +125,117 @@ pub struct Launcher {
     main_window: HideOnDrop<ui::LauncherWindow>,
     sender: AppSender<Message>,
     search: FuzzySearch<1, SearchEntry>,
+    /// Manual pages are searched separately, and only when the query starts with [MAN_PREFIX]
+    man_search: FuzzySearch<1, SearchEntry>,
+    /// Whether the current query is searching manual pages
+    searching_man_pages: bool,
+    /// Whether manual pages have been (or are being) looked for
+    man_pages_scoured: bool,
     bias: LaunchHistory,
     settings: LauncherSettings,
+    select_action: SelectAction,
+    /// What to output when stopped, if the selected entry was not launched.
+    selection: Option<String>,
+    filter: Rc<EntryFilter>,
+    order: Rc<EntryOrder>,
+    /// Which name entries are labelled by, shared with the model given to the UI
+    primary_label: Rc<Cell<PrimaryLabel>>,
+    /// How long typing must pause before the query is searched for, shared with the search box
+    search_debounce: Rc<Cell<Duration>>,
+    query: String,
+    /// The query the entries were last ranked for. While it stays the same, ranking them again
+    /// (as entries are found) keeps the selected entry selected, see [Launcher::keep_selection].
+    ranked_query: String,
+    /// Whether the user moved the selection since the query last changed, shared with the UI.
+    /// Until they do, the best match stays selected, whichever entry that is.
+    selection_moved: Rc<Cell<bool>>,
+    recall: QueryRecall,
+    icon_cache_stats: Cell<IconCacheStats>,
+    /// How many background tasks, other than loading icons, are going on
+    busy: usize,
+    /// How many icons are being loaded in the background
+    pending_icons: Cell<usize>,
+    /// Limits how many of those are actually being decoded at the same time
+    icon_loads: Arc<Semaphore>,
+    /// Entries whose icon is animated, and is being shown frame by frame. Clearing the flag stops
+    /// the task that animates it.
+    animating_icons: RefCell<HashMap<EntryId, Rc<Cell<bool>>>>,
+    /// An application that is launched as soon as its activation token arrives
+    pending_launch: Option<PendingLaunch>,
+    /// Whether an application was launched, but isn't known to be running yet
+    launching: bool,
+    /// Whether the window is animating out and will close when it's done, or is stopped along
+    /// with the daemon
+    closing: bool,
+    /// The desktop file of the entry that was selected when the launcher last closed, to select
+    /// again once it shows up. See [LastQuery].
+    restored_selection: Option<PathBuf>,
+    /// The ids of all desktop entries added so far, as they are sent again when the launcher is
+    /// reopened. See [crate::xdg::DesktopEntry::id].
+    desktop_ids: HashSet<String>,
+    /// The file to open with the selected application, if any
+    open_with: Option<OpenWith>,
+    /// The name of the output the launcher was shown on when it was hidden, see [App::can_reopen]
+    hidden_on_output: Option<String>,
+    /// The entry shown in the preview, see [Message::Preview]
+    previewed: Option<EntryId>,
+    /// The entry whose details are shown, see [Message::ShowInfo]
+    info_shown_for: Option<EntryId>,
+}
+
+/// A file to pick an application for, see [AppOptions::file].
+struct OpenWith {
+    file: PathBuf,
+    /// `None` if it couldn't be found out, in which case every application is offered
+    mime_type: Option<String>,
+}
+
+/// See [Launcher::request_activation_token].
+struct PendingLaunch {
+    serial: AsyncRequestSerial,
+    desktop: Arc<DesktopEntry>,
+    wrapper: Option<PathBuf>,
 }
 
 impl App for Launcher {
     type Message = Message;
-    type Output = JsonAppResult<()>;
+    type Output = JsonAppResult<Option<String>>;
 
     const NAME: AppName = AppName::Launcher;
 
-    fn create(message_sender: AppSender<Self::Message>) -> Self {
+    fn create(message_sender: AppSender<Self::Message>, options: &AppOptions) -> Self {
         // read the bias and settings from persistent state, if any.
         let bias = Self::read_state::<LaunchHistory>().ok().unwrap_or_default();
-        let settings = Self::read_state::<LauncherSettings>()
-            .unwrap_or_default()
-            .sanitize();
+        let settings = read_settings();
+        let icon_loads = Arc::new(Semaphore::new(settings.max_icon_loads as usize));
+        set_icon_theme(settings.icon_theme.clone());
 
         let main_window: HideOnDrop<ui::LauncherWindow> =
             ui::LauncherWindow::new().unwrap().hide_on_drop();
 
         let model: LauncherEntriesModel = Default::default();
+        let filter: Rc<EntryFilter> = Default::default();
+        let order: Rc<EntryOrder> = Default::default();
+        let primary_label: Rc<Cell<PrimaryLabel>> = Default::default();
+        let search_debounce: Rc<Cell<Duration>> = Default::default();
+        let selection_moved: Rc<Cell<bool>> = Default::default();
 
         {
-            let bias = bias.clone();
+            let filter = filter.clone();
+            let order = order.clone();
+            let primary_label = primary_label.clone();
 
-            // The model passed to the UI is filtered on the `shown` property on LauncherEntryUi,
+            // The model passed to the UI is filtered by `EntryFilter`,
             // converted to the slint struct that represents each entry.
             let model = model
                 .clone()
-                .filter(|entry| entry.shown)
-                .sort_by(move |a, b| {
-                    let a_bias = bias.score(a.desktop.path.as_path());
-                    let b_bias = bias.score(b.desktop.path.as_path());
-
-                    (a_bias, a.score)
-                        .partial_cmp(&(b_bias, b.score))
-                        .unwrap_or(Ordering::Equal)
-                    // .reverse()
-                })
+                .filter(move |entry| filter.accepts(entry))
+                .sort_by(move |a, b| order.compare(a, b))
                 .reverse()
-                .map(|entry| entry.to_slint());
+                .map(move |entry| entry.to_slint(primary_label.get()));
 
-            main_window
-                .global::<ui::LauncherEntries>()
-                .set_entries(ModelRc::new(model));
+            let entries = main_window.global::<ui::LauncherEntries>();
+            entries.set_entries(ModelRc::new(model));
         }
 
         let search: FuzzySearch<1, SearchEntry> = FuzzySearch::create_with_config({
@@ -90,14 +249,32 @@ impl App for Launcher {
             let _ = std::thread::spawn(move || scour_desktop_entries(message_sender));
         }
 
+        let man_search: FuzzySearch<1, SearchEntry> = FuzzySearch::create_with_config({
+            let mut config = nucleo::Config::DEFAULT;
+            config.prefer_prefix = true;
+            config
+        });
+
+        // follow the desktop's icon theme, unless the user chose one
         {
-            let notify = search.notify();
+            let changes = system_icon_theme_changes();
+            let sender = message_sender.clone();
+            message_sender.spawn(async move {
+                loop {
+                    changes.acquire().await;
+
+                    sender.send_async(Message::SystemIconThemeChanged).await;
+                }
+            });
+        }
+
+        for notify in [search.notify(), man_search.notify()] {
             let sender = message_sender.clone();
             message_sender.spawn(async move {
                 loop {
                     notify.acquire().await;
 
-                    sender.send(Message::SearchUpdated)
+                    sender.send_async(Message::SearchUpdated).await;
                 }
             });
         }
@@ -105,10 +282,17 @@ impl App for Launcher {
         // On search query edit
         {
             let message_sender = message_sender.clone();
+            let search_debounce = search_debounce.clone();
             main_window
                 .global::<ui::LauncherSearch>()
                 .on_search_edited(move |query| {
-                    message_sender.send(Message::QuerySet(query.as_str().to_string()));
+                    let message = Message::QuerySet(query.as_str().to_string());
+                    match search_debounce.get() {
+                        delay if delay.is_zero() => {
+                            message_sender.send_or_queue(message);
+                        }
+                        delay => message_sender.send_debounced(message, delay),
+                    }
                 });
         }
 
@@ -116,7 +300,7 @@ impl App for Launcher {
         {
             let message_sender = message_sender.clone();
             main_window.on_escape_pressed(move || {
-                message_sender.finish();
+                message_sender.send_or_queue(Message::Close);
             });
         }
 
@@ -128,29 +312,321 @@ impl App for Launcher {
                     return;
                 }
 
-                message_sender.send(Message::Launch(EntryId(id as usize)))
+                message_sender.send_or_queue(Message::Launch(EntryId(id as usize)));
+            });
+        }
+
+        {
+            let selection_moved = selection_moved.clone();
+            main_window.on_selection_moved(move || selection_moved.set(true));
+        }
+
+        {
+            let message_sender = message_sender.clone();
+            main_window.on_launch_elevated(move |id| {
+                if id < 0 {
+                    return;
+                }
+
+                message_sender.send_or_queue(Message::LaunchElevated(EntryId(id as usize)));
             });
         }
 
         {
             let message_sender = message_sender.clone();
             main_window.on_transparency_changed(move |transparency| {
-                message_sender.send(Message::TransparencySet(transparency));
+                message_sender.send_or_queue(Message::TransparencySet(transparency));
+            });
+        }
+
+        {
+            let message_sender = message_sender.clone();
+            main_window.on_toggle_hidden(move |id| {
+                if id < 0 {
+                    return;
+                }
+
+                message_sender.send_or_queue(Message::ToggleHidden(EntryId(id as usize)));
+            });
+        }
+
+        {
+            let message_sender = message_sender.clone();
+            main_window.on_show_hidden_changed(move |show| {
+                message_sender.send_or_queue(Message::ShowHiddenSet(show));
+            });
+        }
+
+        {
+            let message_sender = message_sender.clone();
+            main_window.on_show_commands_changed(move |show| {
+                message_sender.send_or_queue(Message::ShowCommandsSet(show));
+            });
+        }
+
+        {
+            let message_sender = message_sender.clone();
+            main_window.on_match_exec_changed(move |match_exec| {
+                message_sender.send_or_queue(Message::MatchExecSet(match_exec));
+            });
+        }
+
+        {
+            let message_sender = message_sender.clone();
+            main_window.on_exec_weight_changed(move |weight| {
+                message_sender.send_or_queue(Message::ExecWeightSet(weight));
+            });
+        }
+
+        {
+            let message_sender = message_sender.clone();
+            main_window.on_max_icon_loads_changed(move |loads| {
+                message_sender.send_or_queue(Message::MaxIconLoadsSet(loads.max(1) as u32));
+            });
+        }
+
+        {
+            let message_sender = message_sender.clone();
+            main_window.on_animation_duration_ms_changed(move |duration| {
+                message_sender.send_or_queue(Message::AnimationDurationSet(duration.max(0) as u32));
+            });
+        }
+
+        {
+            let message_sender = message_sender.clone();
+            main_window.on_restore_query_secs_changed(move |secs| {
+                message_sender.send_or_queue(Message::RestoreQuerySecsSet(secs.max(0) as u32));
+            });
+        }
+
+        {
+            let message_sender = message_sender.clone();
+            main_window.on_search_debounce_ms_changed(move |delay| {
+                message_sender.send_or_queue(Message::SearchDebounceSet(delay.max(0) as u32));
+            });
+        }
+
+        {
+            let message_sender = message_sender.clone();
+            main_window.on_max_query_len_changed(move |len| {
+                message_sender.send_or_queue(Message::MaxQueryLenSet(len.max(0) as u32));
+            });
+        }
+
+        {
+            let message_sender = message_sender.clone();
+            main_window.on_highlight_color_changed(move |color| {
+                message_sender.send_or_queue(Message::HighlightColorSet(color.to_string()));
+            });
+        }
+
+        {
+            let message_sender = message_sender.clone();
+            main_window.on_highlight_text_color_changed(move |color| {
+                message_sender.send_or_queue(Message::HighlightTextColorSet(color.to_string()));
+            });
+        }
+
+        {
+            let message_sender = message_sender.clone();
+            main_window.on_prefer_shorter_names_changed(move |prefer| {
+                message_sender.send_or_queue(Message::PreferShorterNamesSet(prefer));
+            });
+        }
+
+        {
+            let message_sender = message_sender.clone();
+            main_window.on_hover_selects_changed(move |hover| {
+                message_sender.send_or_queue(Message::HoverSelectsSet(hover));
+            });
+        }
+
+        {
+            let message_sender = message_sender.clone();
+            main_window.on_single_click_launches_changed(move |single| {
+                message_sender.send_or_queue(Message::SingleClickLaunchesSet(single));
+            });
+        }
+
+        {
+            let message_sender = message_sender.clone();
+            main_window.on_wheel_moves_selection_changed(move |moves| {
+                message_sender.send_or_queue(Message::WheelMovesSelectionSet(moves));
+            });
+        }
+
+        {
+            let message_sender = message_sender.clone();
+            main_window.on_recall_query(move |older| {
+                message_sender.send_or_queue(Message::RecallQuery(older));
+            });
+        }
+
+        {
+            let message_sender = message_sender.clone();
+            main_window.on_search_query_history(move |query| {
+                message_sender.send_or_queue(Message::SearchQueryHistory(query.into()));
+            });
+        }
+
+        {
+            let message_sender = message_sender.clone();
+            main_window.on_complete(move |id| {
+                if id < 0 {
+                    return;
+                }
+
+                message_sender.send_or_queue(Message::Complete(EntryId(id as usize)));
+            });
+        }
+
+        {
+            let message_sender = message_sender.clone();
+            main_window.on_show_info(move |id| {
+                if id < 0 {
+                    return;
+                }
+
+                message_sender.send_or_queue(Message::ShowInfo(EntryId(id as usize)));
+            });
+        }
+
+        {
+            let message_sender = message_sender.clone();
+            main_window.on_icon_theme_changed(move |theme| {
+                message_sender.send_or_queue(Message::IconThemeSet(theme.trim().to_string()));
+            });
+        }
+
+        {
+            let message_sender = message_sender.clone();
+            main_window.on_show_preview_changed(move |show| {
+                message_sender.send_or_queue(Message::ShowPreviewSet(show));
+            });
+        }
+
+        {
+            let message_sender = message_sender.clone();
+            main_window.on_show_icons_changed(move |show| {
+                message_sender.send_or_queue(Message::ShowIconsSet(show));
+            });
+        }
+
+        {
+            let message_sender = message_sender.clone();
+            main_window.on_generic_names_first_changed(move |first| {
+                let label = if first {
+                    PrimaryLabel::GenericName
+                } else {
+                    PrimaryLabel::Name
+                };
+                message_sender.send_or_queue(Message::PrimaryLabelSet(label));
+            });
+        }
+
+        {
+            let message_sender = message_sender.clone();
+            main_window.on_keep_warm_changed(move |warm| {
+                message_sender.send_or_queue(Message::KeepWarmSet(warm));
+            });
+        }
+
+        {
+            let message_sender = message_sender.clone();
+            main_window.on_apply_exec_env_changed(move |apply| {
+                message_sender.send_or_queue(Message::ApplyExecEnvSet(apply));
+            });
+        }
+
+        {
+            let message_sender = message_sender.clone();
+            main_window.on_preview_requested(move |id| {
+                if id < 0 {
+                    return;
+                }
+
+                message_sender.send_or_queue(Message::Preview(EntryId(id as usize)));
+            });
+        }
+
+        {
+            let message_sender = message_sender.clone();
+            main_window.on_context_menu(move |id| {
+                if id < 0 {
+                    return;
+                }
+
+                message_sender.send_or_queue(Message::ContextMenu(EntryId(id as usize)));
+            });
+        }
+
+        {
+            let message_sender = message_sender.clone();
+            main_window.on_copy_path(move |id| {
+                if id < 0 {
+                    return;
+                }
+
+                message_sender.send_or_queue(Message::CopyPath(EntryId(id as usize)));
             });
         }
 
-        main_window.show().unwrap();
+        {
+            let message_sender = message_sender.clone();
+            main_window.window().on_winit_window_event(move |_, event| {
+                if let WindowEvent::ActivationTokenDone { serial, token } = event {
+                    message_sender.send_or_queue(Message::ActivationTokenDone(
+                        *serial,
+                        token.clone().into_raw(),
+                    ));
+                }
+
+                WinitWindowEventResult::Propagate
+            });
+        }
 
         let mut launcher = Launcher {
             entries: model,
             bias,
             search,
+            man_search,
+            searching_man_pages: false,
+            man_pages_scoured: false,
             main_window,
             sender: message_sender,
             settings,
+            select_action: options.select_action,
+            selection: None,
+            filter,
+            order,
+            primary_label,
+            search_debounce,
+            query: String::new(),
+            ranked_query: String::new(),
+            selection_moved,
+            recall: QueryRecall::load::<Self>(),
+            icon_cache_stats: Default::default(),
+            busy: 0,
+            pending_icons: Cell::new(0),
+            icon_loads,
+            animating_icons: Default::default(),
+            pending_launch: None,
+            launching: false,
+            closing: false,
+            restored_selection: None,
+            desktop_ids: HashSet::new(),
+            open_with: None,
+            hidden_on_output: None,
+            previewed: None,
+            info_shown_for: None,
         };
 
-        launcher.apply_settings();
+        // commands are only shown when they make sense, see `EntryFilter`
+        for command in LauncherCommand::iter() {
+            launcher.add_entry(next_id(), EntryKind::Command(command));
+        }
+
+        launcher.open(options);
 
         launcher
     }
@@ -158,124 +634,1511 @@ impl App for Launcher {
     fn on_message(&mut self, message: Self::Message) {
         match message {
             Message::QuerySet(query) => {
-                self.search.search::<0>(query);
+                // already searched for, because it was launched before its debounce ran out
+                if query == self.query {
+                    return;
+                }
+
+                self.recall.reset();
+                self.restored_selection = None;
+
+                let max_len = self.settings.max_query_len as usize;
+                match truncate_query(&query, max_len) {
+                    Some(truncated) => {
+                        log::warn!(
+                            "query of {} characters is too long, truncating it to {max_len}",
+                            query.chars().count()
+                        );
+                        // also truncate what is shown, so the input doesn't have to lay out all of it
+                        self.set_query(truncated.to_string());
+                    }
+                    None => self.search_for(query),
+                }
             }
             Message::Launch(entry_id) => {
-                if let Some(LauncherEntry { desktop, .. }) =
-                    self.entries.get_value_of_key(&entry_id)
-                {
-                    self.bias.increment_and_decay(desktop.path.clone());
-                    if let Err(e) = Self::write_state(&self.bias) {
-                        log::error!("couldn't write launcher bias (scoring): {e}");
-                    }
+                let Some(entry_id) = self.entry_to_launch(entry_id) else {
+                    return;
+                };
+                let Some(LauncherEntry { kind, .. }) = self.entries.get_value_of_key(&entry_id)
+                else {
+                    return;
+                };
 
-                    if let Err(e) = launch(desktop.as_ref()) {
-                        log::error!("failed to launch: {e}")
-                    }
-                    self.sender.finish();
+                self.recall.record::<Self>(&self.query);
+
+                match kind {
+                    EntryKind::Desktop(desktop) => self.select_desktop_entry(desktop, None),
+                    EntryKind::Command(command) => self.run_command(command),
+                    EntryKind::ManPage(page) => self.select_man_page(&page),
                 }
+
+                self.finish();
+            }
+            Message::LaunchElevated(entry_id) => {
+                // only applications can be elevated, and only if they are launched at all.
+                if self.select_action != SelectAction::Launch {
+                    return;
+                }
+
+                let Some(desktop) = self
+                    .entry_to_launch(entry_id)
+                    .and_then(|entry_id| self.entries.get_value_of_key(&entry_id))
+                    .and_then(|entry| entry.desktop().cloned())
+                else {
+                    return;
+                };
+
+                let Some(pkexec) = find_program("pkexec") else {
+                    log::error!(
+                        "can't launch {} as another user: pkexec is not installed",
+                        desktop.name
+                    );
+                    return;
+                };
+
+                self.recall.record::<Self>(&self.query);
+                self.select_desktop_entry(desktop, Some(&pkexec));
+
+                self.finish();
             }
             Message::NewEntry(id, entry) => {
-                self.search.push(SearchEntry {
-                    for_id: id,
-                    text: entry.name.clone(),
-                });
-                self.entries
-                    .insert(id, self.launcher_entry_for_desktop(id, entry));
+                // a reopened launcher is sent the entries it already has again
+                if self.desktop_ids.insert(entry.id.clone()) {
+                    self.keep_selection(|launcher| {
+                        launcher.add_entry(id, EntryKind::Desktop(entry))
+                    });
+                }
+            }
+            Message::NewManPage(id, page) => {
+                self.keep_selection(|launcher| launcher.add_entry(id, EntryKind::ManPage(page)));
             }
             Message::UpdateIcon(id, icon) => {
+                self.pending_icons.set(self.pending_icons.get() - 1);
+                self.show_busy();
+
+                let Some(icon) = icon else {
+                    return;
+                };
+
                 self.entries.mutate_by_key(&id, |_, _, v| {
                     v.icon = Some(icon);
                 });
+                self.animate_icon(id);
             }
+            Message::NextIconFrame(id, frame) => self.show_icon_frame(id, frame),
             Message::SearchUpdated => {
-                self.search.tick();
-
-                let matches: Vec<_> = self
-                    .search
-                    .get_matches()
-                    .into_iter()
-                    .map(|entry| entry.for_id)
-                    .collect();
-
-                self.entries.mutate_all(|_, entry_id, v| {
-                    let position = matches
-                        .iter()
-                        .position(|x| x == entry_id)
-                        .map(|pos| matches.len() - pos);
-                    v.shown = position.is_some();
-                    v.score = position.unwrap_or_default() as u32;
-                });
+                // only the active search decides what is shown: entries from the other one have
+                // no score, and are hidden.
+                let search = if self.searching_man_pages {
+                    &mut self.man_search
+                } else {
+                    &mut self.search
+                };
+                search.tick();
+
+                let matches = search.get_scored_matches();
+                let query = man_query(&self.query).unwrap_or(&self.query);
+                let scores = score_matches(query, &matches, &self.settings);
+
+                // when the query changed, the best match is what should be selected; otherwise,
+                // entries were only found, which must not move the selection from under the user.
+                let update_scores = |launcher: &mut Self| {
+                    launcher.entries.mutate_all(|_, entry_id, v| {
+                        let score = scores.get(entry_id);
+                        v.shown = score.is_some();
+                        v.score = score.copied().unwrap_or_default();
+                    });
+                };
+                if self.query == self.ranked_query {
+                    self.keep_selection(update_scores);
+                } else {
+                    update_scores(self);
+                    self.ranked_query.clone_from(&self.query);
+                    self.selection_moved.set(false);
+                }
+
+                self.restore_selection();
             }
             Message::TransparencySet(trans) => {
                 self.settings.transparency = trans;
             }
-        }
-    }
+            Message::ToggleHidden(entry_id) => {
+                // only desktop entries may be hidden
+                let Some((path, hidden)) = self
+                    .entries
+                    .mutate_by_key(&entry_id, |_, _, v| {
+                        let path = v.desktop()?.path.clone();
+                        v.hidden = !v.hidden;
 
-    fn stop(self) -> Self::Output {
-        // save settings, then quit
-        if let Err(e) = Self::write_state(&self.settings) {
-            log::error!("couldn't write settings: {e}");
-        }
+                        Some((path, v.hidden))
+                    })
+                    .flatten()
+                else {
+                    return;
+                };
 
-        JsonAppResult(())
-    }
-}
+                if hidden {
+                    self.settings.hidden_entries.insert(path);
+                } else {
+                    self.settings.hidden_entries.remove(&path);
+                }
 
-impl Launcher {
-    fn launcher_entry_for_desktop(&self, id: EntryId, entry: Arc<DesktopEntry>) -> LauncherEntry {
-        // Icon loading is offloaded and cached.
-        // if we've already got an icon for this entry, or it has failed before,
-        // we don't try again:
-        let icon = if let Some(icon_path) = entry.icon.as_deref() {
-            if is_icon_cached(icon_path) {
-                // great! load_icon won't block:
-                load_icon(icon_path)
-            } else {
-                // no cache hit -> we'll have to offload this, and update it later.
-                let icon_path = icon_path.to_string();
-                let sender = self.sender.clone();
-                let offloaded_task = smol::unblock(move || load_icon(&icon_path));
-
-                drop(slint::spawn_local(async move {
-                    let icon = offloaded_task.await;
-                    if let Some(icon) = icon {
-                        sender.send(Message::UpdateIcon(id, icon));
+                // persist immediately, rather than only when the launcher stops
+                if let Err(e) = Self::write_config(&self.settings) {
+                    log::error!("couldn't write settings: {e}");
+                }
+            }
+            Message::ShowHiddenSet(show) => {
+                self.filter.show_hidden.set(show);
+                // the filter changed, so the model must be re-evaluated entirely
+                self.entries.mutate_all(|_, _, _| {});
+            }
+            Message::ShowCommandsSet(show) => {
+                self.settings.show_commands = show;
+                self.filter.show_commands.set(show);
+                self.entries.mutate_all(|_, _, _| {});
+            }
+            Message::MatchExecSet(match_exec) => {
+                self.settings.match_exec = match_exec;
+                // re-rank the current matches with the new setting
+                self.sender.send_or_queue(Message::SearchUpdated);
+            }
+            Message::MaxIconLoadsSet(loads) => {
+                self.settings.max_icon_loads = loads;
+            }
+            Message::RestoreQuerySecsSet(secs) => {
+                self.settings.restore_query_secs = secs;
+            }
+            Message::MaxQueryLenSet(len) => {
+                self.settings.max_query_len = len;
+                self.settings = std::mem::take(&mut self.settings).sanitize();
+            }
+            Message::AnimationDurationSet(duration) => {
+                self.settings.animation_duration_ms = duration;
+                self.settings = std::mem::take(&mut self.settings).sanitize();
+            }
+            Message::SearchDebounceSet(delay) => {
+                self.settings.search_debounce_ms = delay;
+                self.settings = std::mem::take(&mut self.settings).sanitize();
+                self.apply_settings();
+            }
+            Message::Close => self.finish(),
+            Message::ExecWeightSet(weight) => {
+                self.settings.exec_weight = weight;
+                self.sender.send_or_queue(Message::SearchUpdated);
+            }
+            Message::HighlightColorSet(color) => {
+                self.settings.highlight_color = color;
+                // invalid colors are replaced by the default when sanitizing
+                self.settings = std::mem::take(&mut self.settings).sanitize();
+                self.apply_settings();
+            }
+            Message::HighlightTextColorSet(color) => {
+                self.settings.highlight_text_color = (!color.is_empty()).then_some(color);
+                self.settings = std::mem::take(&mut self.settings).sanitize();
+                self.apply_settings();
+            }
+            Message::PreferShorterNamesSet(prefer) => {
+                self.settings.prefer_shorter_names = prefer;
+                self.order.prefer_shorter_names.set(prefer);
+                // the order changed, so the model must be sorted again
+                self.entries.mutate_all(|_, _, _| {});
+            }
+            Message::HoverSelectsSet(hover) => {
+                self.settings.hover_selects = hover;
+            }
+            Message::SingleClickLaunchesSet(single) => {
+                self.settings.single_click_launches = single;
+            }
+            Message::WheelMovesSelectionSet(moves) => {
+                self.settings.wheel_moves_selection = moves;
+            }
+            Message::RecallQuery(older) => {
+                let query = self.recall.step(older);
+
+                self.set_query(query);
+                self.main_window
+                    .set_recalling_query(self.recall.is_recalling());
+            }
+            Message::SearchQueryHistory(query) => {
+                // nothing (older) matches: keep what is there, as a shell does
+                let Some(query) = self.recall.search(&query) else {
+                    return;
+                };
+
+                self.set_query(query);
+                self.main_window.set_recalling_query(true);
+            }
+            Message::ShowInfo(entry_id) => {
+                // only applications have a desktop file to show
+                let Some(desktop) = self
+                    .entries
+                    .get_value_of_key(&entry_id)
+                    .and_then(|entry| entry.desktop().cloned())
+                else {
+                    return;
+                };
+
+                let contents = std::fs::read_to_string(&desktop.path).unwrap_or_else(|e| {
+                    log::error!("couldn't read {}: {e}", desktop.path.display());
+                    String::new()
+                });
+                self.info_shown_for = Some(entry_id);
+                let icon = self.load_large_icon_for(entry_id, LargeIconFor::Info, &desktop);
+
+                self.main_window.set_info(ui::EntryInfo {
+                    name: desktop.name.clone(),
+                    path: desktop.path.to_string_lossy().as_ref().into(),
+                    icon,
+                    contents: contents.into(),
+                });
+                self.main_window.set_info_shown(true);
+            }
+            Message::ShowPreviewSet(show) => {
+                self.settings.show_preview = show;
+            }
+            Message::ShowIconsSet(show) => {
+                self.settings.show_icons = show;
+                self.apply_settings();
+                // loads the icons, or drops them to free their memory
+                self.reload_icons();
+            }
+            Message::PrimaryLabelSet(label) => {
+                self.settings.primary_label = label;
+                self.apply_settings();
+                // relabel the entries, and rank them by what they are now labelled
+                self.entries.mutate_all(|_, _, _| {});
+                self.sender.send_or_queue(Message::SearchUpdated);
+            }
+            Message::KeepWarmSet(warm) => {
+                self.settings.keep_warm = warm;
+            }
+            Message::ApplyExecEnvSet(apply) => {
+                self.settings.apply_exec_env = apply;
+            }
+            Message::IconThemeSet(theme) => {
+                self.settings.icon_theme = (!theme.is_empty()).then_some(theme);
+                self.apply_settings();
+
+                // a theme that isn't installed is kept (it may be installed later), but the default
+                // one is used in its place
+                if set_icon_theme(self.settings.icon_theme.clone()) {
+                    self.reload_icons();
+                }
+            }
+            Message::FileTypeFound(file, mime_type, default_application) => {
+                // the launcher may have been reopened for another file since
+                if self.open_with.as_ref().map(|open_with| &open_with.file) != Some(&file) {
+                    return;
+                }
+
+                self.set_file_type(mime_type, default_application);
+                // re-filter and re-order what is already there
+                self.entries.mutate_all(|_, _, _| {});
+            }
+            Message::LargeIconLoaded(shown_in, entry_id, icon) => {
+                // the selection may have moved on while it loaded
+                let icon = slint::Image::from_rgba8(icon);
+                match shown_in {
+                    LargeIconFor::Preview if self.previewed == Some(entry_id) => {
+                        let mut preview = self.main_window.get_preview();
+                        preview.icon = icon;
+                        self.main_window.set_preview(preview);
                     }
-                }));
+                    LargeIconFor::Info if self.info_shown_for == Some(entry_id) => {
+                        let mut info = self.main_window.get_info();
+                        info.icon = icon;
+                        self.main_window.set_info(info);
+                    }
+                    LargeIconFor::Preview | LargeIconFor::Info => {}
+                }
+            }
+            Message::SystemIconThemeChanged => {
+                // the icons from the previous theme have already been dropped
+                self.reload_icons();
+            }
+            Message::Preview(entry_id) => {
+                // selecting entries quickly may queue up previews of entries that aren't selected
+                // anymore, and the preview may have been turned off since.
+                if !self.settings.show_preview {
+                    return;
+                }
+
+                let Some(desktop) = self
+                    .entries
+                    .get_value_of_key(&entry_id)
+                    .and_then(|entry| entry.desktop().cloned())
+                else {
+                    self.main_window.set_preview_available(false);
+                    return;
+                };
+
+                self.previewed = Some(entry_id);
+                let icon = self.load_large_icon_for(entry_id, LargeIconFor::Preview, &desktop);
+
+                self.main_window.set_preview(ui::EntryPreview {
+                    icon,
+                    name: desktop.name.clone(),
+                    description: desktop.description.clone().unwrap_or_default(),
+                    categories: desktop.categories.join(", ").into(),
+                    keywords: desktop.keywords.join(", ").into(),
+                    exec: desktop.display_exec().into(),
+                    path: desktop.path.to_string_lossy().as_ref().into(),
+                    overrides: desktop
+                        .overrides
+                        .as_ref()
+                        .map(|path| path.to_string_lossy().as_ref().into())
+                        .unwrap_or_default(),
+                });
+                self.main_window.set_preview_available(true);
+            }
+            Message::ContextMenu(entry_id) => {
+                let Some(entry) = self.entries.get_value_of_key(&entry_id) else {
+                    return;
+                };
+                let is_application = entry.desktop().is_some();
+
+                self.main_window.set_entry_menu(ui::EntryMenu {
+                    id: entry_id.0 as i32,
+                    is_application,
+                    can_elevate: is_application
+                        && self.select_action == SelectAction::Launch
+                        && find_program("pkexec").is_some(),
+                    hidden: entry.hidden,
+                });
+                self.main_window.invoke_show_entry_menu();
+            }
+            Message::CopyPath(entry_id) => {
+                let Some(desktop) = self
+                    .entries
+                    .get_value_of_key(&entry_id)
+                    .and_then(|entry| entry.desktop().cloned())
+                else {
+                    return;
+                };
+
+                if let Err(e) = copy_to_clipboard(&desktop.path.to_string_lossy()) {
+                    let error = format!("Couldn't copy the path of {}: {e}", desktop.name);
+                    self.show_error(error, ERROR_DURATION);
+                }
+            }
+            Message::ActivationTokenDone(serial, token) => {
+                self.launch_pending(serial, Some(&token));
+            }
+            Message::ActivationTokenTimedOut(serial) => {
+                log::warn!("compositor did not hand out an activation token in time");
+                self.launch_pending(serial, None);
+            }
+            Message::Launched => {
+                self.launching = false;
+                self.finish();
+            }
+            Message::LaunchFailed(error) => {
+                // stay open, so the user sees what went wrong.
+                self.launching = false;
+                self.show_error(error, ERROR_DURATION);
+            }
+            Message::ShowError(error, duration) => self.show_error(error, duration),
+            Message::ErrorExpired(error) => {
+                // a newer error may have replaced this one, which should stay a while longer.
+                if self.main_window.get_error_message() == error.as_str() {
+                    self.main_window.set_error_message(Default::default());
+                }
+            }
+            Message::Complete(entry_id) => {
+                let Some(entry) = self.entries.get_value_of_key(&entry_id) else {
+                    return;
+                };
 
-                None
+                self.recall.reset();
+                self.main_window.set_recalling_query(false);
+                let name = entry.name();
+                let query = match entry.kind {
+                    EntryKind::ManPage(_) => format!("{MAN_PREFIX} {name}"),
+                    _ => name.to_string(),
+                };
+                self.set_query(query);
+                // the completed entry will be the best match
+                self.main_window.set_selected_entry(0);
             }
+        }
+    }
+
+    fn set_busy(&mut self, busy: bool) {
+        // entries and manual pages may be scoured at the same time
+        if busy {
+            self.busy += 1;
         } else {
-            None // no icon_path, no icon.
+            self.busy = self.busy.saturating_sub(1);
+        }
+        self.show_busy();
+    }
+
+    fn serialize_state(&self) -> serde_json::Value {
+        let IconCacheStats { hits, misses } = self.icon_cache_stats.get();
+        let icon_cache_hit_rate = (hits + misses > 0).then(|| hits as f32 / (hits + misses) as f32);
+
+        serde_json::json!({
+            "query": self.query,
+            "entries": self.entries.row_count(),
+            "icon_cache_hit_rate": icon_cache_hit_rate,
+            "history_size": self.bias.entry_count(),
+            "select_action": format!("{:?}", self.select_action),
+        })
+    }
+
+    fn hide(&mut self) -> Option<Self::Output> {
+        if !self.settings.keep_warm {
+            return None;
+        }
+
+        self.save_state();
+        self.hidden_on_output = self
+            .main_window
+            .window()
+            .with_winit_window(|window| window.current_monitor()?.name())
+            .flatten();
+        if let Err(e) = self.main_window.hide() {
+            log::error!("couldn't hide the launcher: {e}");
+        }
+
+        Some(JsonAppResult(self.selection.take()))
+    }
+
+    fn can_reopen(&self) -> bool {
+        let Some(output) = &self.hidden_on_output else {
+            return true;
+        };
+
+        // the window was made for that output: if it's gone (e.g. a monitor was unplugged), so is
+        // the point of keeping the window around.
+        self.main_window
+            .window()
+            .with_winit_window(|window| {
+                window
+                    .available_monitors()
+                    .any(|monitor| monitor.name().as_ref() == Some(output))
+            })
+            .unwrap_or(true)
+    }
+
+    fn reopen(&mut self, options: &AppOptions) {
+        // back to how a newly created launcher starts out
+        self.selection = None;
+        self.pending_launch = None;
+        self.launching = false;
+        self.closing = false;
+        self.restored_selection = None;
+        self.recall.reset();
+        self.main_window.set_recalling_query(false);
+        self.main_window.set_info_shown(false);
+        self.main_window.set_error_message(Default::default());
+        self.set_query(String::new());
+        self.main_window.set_selected_entry(0);
+        self.selection_moved.set(false);
+        self.main_window.invoke_focus_query();
+
+        // what was launched since the launcher was created changes how entries rank, and entries
+        // may have been forgotten from the launch stats in the meantime.
+        self.bias = Self::read_state::<LaunchHistory>().ok().unwrap_or_default();
+        let bias = &self.bias;
+        self.entries.mutate_all(|_, _, entry| {
+            if let Some(desktop) = entry.desktop().cloned() {
+                entry.bias = bias.score(&desktop.path);
+                entry.last_launched = bias.last_launched(&desktop.path);
+            }
+        });
+
+        // applications may have been installed while the launcher was hidden
+        {
+            let message_sender = self.sender.clone();
+            let _ = std::thread::spawn(move || scour_desktop_entries(message_sender));
+        }
+
+        self.open(options);
+    }
+
+    fn screenshot(&self) -> Option<Screenshot> {
+        self.main_window.window().take_snapshot().ok()
+    }
+
+    fn stop(self) -> Self::Output {
+        // save settings, then quit
+        self.save_state();
+
+        JsonAppResult(self.selection)
+    }
+}
+
+impl Launcher {
+    /// Show the window, set up for `options`. This is the part of creating the launcher that is
+    /// done again when it is reopened.
+    fn open(&mut self, options: &AppOptions) {
+        self.select_action = options.select_action;
+        // every application is offered until the file's type is known
+        self.open_with = options.file.clone().map(|file| OpenWith {
+            file,
+            mime_type: None,
+        });
+        self.set_file_type(None, None);
+        if let Some(file) = options.file.clone() {
+            self.find_file_type(file);
+        }
+
+        // commands don't make sense when the selection is printed rather than launched, or when
+        // it opens a file
+        self.filter
+            .commands_apply
+            .set(self.select_action == SelectAction::Launch && self.open_with.is_none());
+
+        self.main_window.set_open_with_file(
+            self.open_with
+                .as_ref()
+                .and_then(|open_with| open_with.file.file_name())
+                .map(|name| name.to_string_lossy().as_ref().into())
+                .unwrap_or_default(),
+        );
+        self.main_window.set_remember_association(false);
+        // re-filter and re-order what is already there
+        let mut ids = Vec::new();
+        self.entries.mutate_all(|_, id, _| ids.push(*id));
+
+        // when picking an entry for someone else, say so: compositor rules may want to treat
+        // this differently from the regular launcher.
+        let state = match options.select_action {
+            SelectAction::Launch if self.open_with.is_some() => Some("open with"),
+            SelectAction::Launch => None,
+            SelectAction::PrintPath | SelectAction::PrintExec => Some("select"),
+        };
+        self.main_window
+            .set_window_title(window_title(Self::NAME, state));
+        self.main_window
+            .global::<ui::LauncherEntries>()
+            .set_debug_scores(options.debug_scores);
+
+        self.apply_settings();
+        self.main_window.show().unwrap();
+        // the window is open: animate it in.
+        self.main_window.set_shown(true);
+        // icons stop animating while the window is hidden
+        ids.into_iter().for_each(|id| self.animate_icon(id));
+
+        // reopened soon after closing: continue where the user left off.
+        let restore_within = Duration::from_secs(self.settings.restore_query_secs.into());
+        if let Some(last) = LastQuery::restore::<Self>(restore_within) {
+            self.set_query(last.query);
+            // typing replaces the restored query, rather than adding to it
+            self.main_window.invoke_select_query();
+            self.restored_selection = last.selection.map(PathBuf::from);
+        }
+    }
+
+    /// Find out the MIME type of `file`, and which application opens it by default:
+    /// [Message::FileTypeFound] follows. `xdg-mime` is a shell script, which takes a moment.
+    fn find_file_type(&self, file: PathBuf) {
+        let sender = self.sender.clone();
+        self.sender.spawn(async move {
+            let (mime_type, default_application) = smol::unblock({
+                let file = file.clone();
+                move || {
+                    let mime_type = mime::mime_type_of(&file);
+                    let default_application =
+                        mime_type.as_deref().and_then(mime::default_application);
+
+                    (mime_type, default_application)
+                }
+            })
+            .await;
+
+            sender
+                .send_async(Message::FileTypeFound(file, mime_type, default_application))
+                .await;
+        });
+    }
+
+    /// Only offer applications that can open files of `mime_type`, `default_application` first.
+    /// With `None`, every application is offered.
+    fn set_file_type(&mut self, mime_type: Option<String>, default_application: Option<String>) {
+        *self.order.preferred.borrow_mut() = default_application;
+        self.main_window
+            .set_open_with_type(mime_type.as_deref().unwrap_or_default().into());
+        *self.filter.mime_type.borrow_mut() = mime_type.clone();
+
+        if let Some(open_with) = &mut self.open_with {
+            open_with.mime_type = mime_type;
+        }
+    }
+
+    /// Persist the settings, and the query the launcher is closed with.
+    fn save_state(&self) {
+        if let Err(e) = Self::write_config(&self.settings) {
+            log::error!("couldn't write settings: {e}");
+        }
+
+        let selection = self
+            .selected_entry()
+            .and_then(|entry| Some(entry.desktop()?.path.to_string_lossy().to_string()));
+        LastQuery::save::<Self>(&self.query, selection);
+    }
+
+    /// Add an entry to the fuzzy matcher and the UI.
+    fn add_entry(&self, id: EntryId, kind: EntryKind) {
+        let entry = self.launcher_entry(id, kind);
+
+        let search = match entry.kind {
+            EntryKind::ManPage(_) => &self.man_search,
+            _ => &self.search,
+        };
+        search.push(SearchEntry {
+            for_id: id,
+            column: SearchColumn::Name,
+            text: entry.name(),
+        });
+
+        // like the program's name below, always pushed so that changing the setting that weighs
+        // it takes effect immediately.
+        if let Some(generic_name) = entry
+            .desktop()
+            .and_then(|desktop| desktop.generic_name.clone())
+        {
+            self.search.push(SearchEntry {
+                for_id: id,
+                column: SearchColumn::GenericName,
+                text: generic_name,
+            });
+        }
+
+        // the program's name is always pushed, even if `match_exec` is off, so that turning it
+        // on takes effect immediately.
+        if let Some(exec) = entry.desktop().and_then(|desktop| desktop.exec_basename()) {
+            self.search.push(SearchEntry {
+                for_id: id,
+                column: SearchColumn::Exec,
+                text: exec.into(),
+            });
+        }
+
+        self.entries.insert(id, entry);
+        self.animate_icon(id);
+    }
+
+    /// Show the busy indicator while desktop entries are being found, or icons are being loaded.
+    fn show_busy(&self) {
+        self.main_window
+            .set_busy(self.busy > 0 || self.pending_icons.get() > 0);
+    }
+
+    /// Replace the query in the search box, and search for it.
+    fn set_query(&mut self, query: String) {
+        self.main_window.set_query(query.as_str().into());
+        self.main_window.invoke_move_cursor_to_end();
+
+        self.search_for(query);
+    }
+
+    /// Search for `query`, among manual pages if it starts with [MAN_PREFIX].
+    fn search_for(&mut self, query: String) {
+        self.query = query.clone();
+
+        let was_searching_man_pages = self.searching_man_pages;
+        self.searching_man_pages = man_query(&query).is_some();
+        if self.searching_man_pages != was_searching_man_pages {
+            // the other search may not have changed, and never notify: rank it regardless.
+            self.sender.send_or_queue(Message::SearchUpdated);
+        }
+
+        let Some(man_query) = man_query(&query) else {
+            self.search.search::<0>(query);
+            return;
+        };
+
+        // looking for manual pages is slow, so it's only done when they're asked for.
+        if !self.man_pages_scoured {
+            self.man_pages_scoured = true;
+
+            let sender = self.sender.clone();
+            let _ = std::thread::spawn(move || scour_man_pages(sender));
+        }
+
+        self.man_search.search::<0>(man_query);
+    }
+
+    fn launcher_entry(&self, id: EntryId, kind: EntryKind) -> LauncherEntry {
+        let (icon, bias, last_launched, hidden) = match &kind {
+            EntryKind::Desktop(desktop) => (
+                self.load_icon_for(id, desktop),
+                self.bias.score(desktop.path.as_path()),
+                self.bias.last_launched(desktop.path.as_path()),
+                self.settings.hidden_entries.contains(&desktop.path),
+            ),
+            EntryKind::Command(_) | EntryKind::ManPage(_) => (None, 0.0, None, false),
         };
 
         LauncherEntry {
             id,
-            shown: true,
-            score: 0,
-            desktop: entry,
+            // manual pages only show up once they match a query for them
+            shown: !matches!(kind, EntryKind::ManPage(_)),
+            score: 0.0,
+            bias,
+            last_launched,
+            hidden,
+            kind,
             icon,
         }
     }
 
+    /// The entry that is selected in the UI, if any.
+    fn selected_entry(&self) -> Option<LauncherEntry> {
+        self.entries.get_value_of_key(&self.selected_entry_id()?)
+    }
+
+    fn selected_entry_id(&self) -> Option<EntryId> {
+        let row = usize::try_from(self.main_window.get_selected_entry()).ok()?;
+        let entry = self
+            .main_window
+            .global::<ui::LauncherEntries>()
+            .get_entries()
+            .row_data(row)?;
+
+        Some(EntryId(entry.id as usize))
+    }
+
+    /// The entry to launch when `selected` was chosen. If the query in the search box wasn't
+    /// searched for yet, because it is debounced (see [LauncherSettings::search_debounce_ms]),
+    /// `selected` was ranked for an older query: the new query is searched for right away, and
+    /// its best match is launched instead.
+    fn entry_to_launch(&mut self, selected: EntryId) -> Option<EntryId> {
+        let query = self.main_window.get_query().to_string();
+        if query == self.query {
+            return Some(selected);
+        }
+
+        self.on_message(Message::QuerySet(query));
+        if self.searching_man_pages {
+            self.man_search.finish();
+        } else {
+            self.search.finish();
+        }
+        self.on_message(Message::SearchUpdated);
+
+        let best = self
+            .main_window
+            .global::<ui::LauncherEntries>()
+            .get_entries()
+            .row_data(0)?;
+
+        Some(EntryId(best.id as usize))
+    }
+
+    /// Run `fun`, which changes the entries shown, and then select the entry that was selected
+    /// before again: its row may have changed, e.g. because entries were added above it. Until the
+    /// user moves the selection, it stays on the best match instead.
+    fn keep_selection(&mut self, fun: impl FnOnce(&mut Self)) {
+        let anchor = self
+            .selection_moved
+            .get()
+            .then(|| self.selected_entry_id())
+            .flatten();
+        fun(self);
+        // the best match is wherever the entries put it
+        let Some(anchor) = anchor else {
+            return;
+        };
+
+        let entries = self
+            .main_window
+            .global::<ui::LauncherEntries>()
+            .get_entries();
+        let rows = entries.iter().map(|entry| EntryId(entry.id as usize));
+        if let Some(row) = row_to_reselect(anchor, self.selected_entry_id(), rows) {
+            // this also scrolls it into view
+            self.main_window.invoke_select_entry(row as i32);
+        }
+    }
+
+    /// Select the entry that was selected when the launcher last closed, once it shows up in the
+    /// results.
+    fn restore_selection(&mut self) {
+        let Some(path) = &self.restored_selection else {
+            return;
+        };
+
+        let row = self
+            .main_window
+            .global::<ui::LauncherEntries>()
+            .get_entries()
+            .iter()
+            .position(|entry| {
+                self.entries
+                    .get_value_of_key(&EntryId(entry.id as usize))
+                    .and_then(|entry| entry.desktop().map(|desktop| &desktop.path == path))
+                    .unwrap_or(false)
+            });
+
+        if let Some(row) = row {
+            self.main_window.set_selected_entry(row as i32);
+            self.restored_selection = None;
+            // as if the user selected it again: keep it selected
+            self.selection_moved.set(true);
+        }
+    }
+
+    /// Load the icons of all entries again, e.g. because the icon theme changed.
+    fn reload_icons(&self) {
+        let mut ids = Vec::new();
+        self.entries.mutate_all(|_, id, entry| {
+            let Some(desktop) = entry.desktop().cloned() else {
+                return;
+            };
+
+            entry.icon = self.load_icon_for(*id, &desktop);
+            ids.push(*id);
+        });
+
+        ids.into_iter().for_each(|id| self.animate_icon(id));
+    }
+
+    /// Start animating the icon of entry `id`, if it is animated and not being animated already.
+    fn animate_icon(&self, id: EntryId) {
+        let Some(frames) = self
+            .entries
+            .get_value_of_key(&id)
+            .and_then(|entry| entry.icon_animation())
+        else {
+            return;
+        };
+
+        if self.animating_icons.borrow().contains_key(&id) {
+            return;
+        }
+        let animating = Rc::new(Cell::new(true));
+        self.animating_icons
+            .borrow_mut()
+            .insert(id, animating.clone());
+
+        // one task per animated icon, for as long as it is animated
+        let sender = self.sender.clone();
+        self.sender.spawn(async move {
+            let mut frame = 0;
+            loop {
+                smol::Timer::after(frames[frame].1).await;
+                if !animating.get() {
+                    return;
+                }
+
+                frame = (frame + 1) % frames.len();
+                sender.send_async(Message::NextIconFrame(id, frame)).await;
+            }
+        });
+    }
+
+    /// Show frame `frame` of the animated icon of entry `id`. Stops the animation once the window
+    /// is hidden, or the entry's icon isn't animated anymore (e.g. because icons were turned off).
+    fn show_icon_frame(&self, id: EntryId, frame: usize) {
+        let entry = self
+            .entries
+            .get_value_of_key(&id)
+            .filter(|_| self.main_window.window().is_visible());
+        let Some((entry, frames)) =
+            entry.and_then(|entry| entry.icon_animation().map(|frames| (entry, frames)))
+        else {
+            if let Some(animating) = self.animating_icons.borrow_mut().remove(&id) {
+                animating.set(false);
+            }
+            return;
+        };
+
+        // changing an entry filters and sorts the model again: not worth it for one that's not
+        // shown anyway.
+        if !entry.shown {
+            return;
+        }
+
+        let (pixels, _) = &frames[frame % frames.len()];
+        self.entries.mutate_by_key(&id, |_, _, entry| {
+            entry.icon = Some(pixels.clone());
+        });
+    }
+
+    fn load_icon_for(&self, id: EntryId, entry: &DesktopEntry) -> Option<Pixels> {
+        // nothing to load, and no task to spawn for it
+        if !self.settings.show_icons {
+            return None;
+        }
+
+        // Icon loading is offloaded and cached.
+        // if we've already got an icon for this entry, or it has failed before,
+        // we don't try again:
+        let icon_path = entry.icon.as_deref()?;
+
+        let mut stats = self.icon_cache_stats.get();
+        let cached = is_icon_cached(icon_path);
+        if cached {
+            stats.hits += 1;
+        } else {
+            stats.misses += 1;
+        }
+        self.icon_cache_stats.set(stats);
+
+        if cached {
+            // great! load_icon won't block:
+            load_icon(icon_path)
+        } else {
+            // no cache hit -> we'll have to offload this, and update it later.
+            let icon_path = icon_path.to_string();
+            let sender = self.sender.clone();
+            let icon_loads = self.icon_loads.clone();
+            // on a cold cache, every entry wants its icon at once: take turns, rather than
+            // flooding the blocking thread pool.
+            let offloaded_task = async move {
+                let _permit = icon_loads.acquire_arc().await;
+                smol::unblock(move || load_icon(&icon_path)).await
+            };
+
+            self.sender.spawn(async move {
+                let icon = offloaded_task.await;
+                sender.send_async(Message::UpdateIcon(id, icon)).await;
+            });
+
+            self.pending_icons.set(self.pending_icons.get() + 1);
+            self.show_busy();
+
+            None
+        }
+    }
+
+    /// Load the large version of `desktop`'s icon in the background, to show in `shown_in`:
+    /// [Message::LargeIconLoaded] follows. Returns what to show until then, which is its regular
+    /// icon.
+    fn load_large_icon_for(
+        &self,
+        id: EntryId,
+        shown_in: LargeIconFor,
+        desktop: &DesktopEntry,
+    ) -> slint::Image {
+        let Some(icon_path) = desktop.icon.clone().filter(|_| self.settings.show_icons) else {
+            return Default::default();
+        };
+
+        let sender = self.sender.clone();
+        self.sender.spawn(async move {
+            if let Some(icon) = smol::unblock(move || load_large_icon(&icon_path)).await {
+                sender
+                    .send_async(Message::LargeIconLoaded(shown_in, id, icon))
+                    .await;
+            }
+        });
+
+        self.entries
+            .get_value_of_key(&id)
+            .and_then(|entry| entry.icon)
+            .map(slint::Image::from_rgba8)
+            .unwrap_or_default()
+    }
+
+    /// The user selected this desktop entry: launch it (through `wrapper`, if any), or remember it
+    /// for output.
+    fn select_desktop_entry(&mut self, desktop: Arc<DesktopEntry>, wrapper: Option<&Path>) {
+        match self.select_action {
+            SelectAction::Launch => {
+                self.bias.increment_and_decay(desktop.path.clone());
+                if let Err(e) = Self::write_state(&self.bias) {
+                    log::error!("couldn't write launcher bias (scoring): {e}");
+                }
+
+                let mime_type = self
+                    .open_with
+                    .as_ref()
+                    .and_then(|open_with| open_with.mime_type.as_deref());
+                if let Some(mime_type) = mime_type {
+                    if self.main_window.get_remember_association() {
+                        let mime_type = mime_type.to_string();
+                        let desktop_id = desktop.id.clone();
+                        smol::unblock(move || {
+                            if let Err(e) = mime::set_default_application(&mime_type, &desktop_id) {
+                                log::error!(
+                                    "couldn't open {mime_type} with {desktop_id} by default: {e}"
+                                );
+                            }
+                        })
+                        .detach();
+                    }
+                }
+
+                // launching is delayed until the compositor gave us a token to pass on, so that
+                // the application's window is focused when it opens.
+                if let Some(serial) = self.request_activation_token() {
+                    self.pending_launch = Some(PendingLaunch {
+                        serial,
+                        desktop,
+                        wrapper: wrapper.map(Path::to_path_buf),
+                    });
+                    return;
+                }
+
+                self.start_launch(&desktop, wrapper, None);
+            }
+            SelectAction::PrintPath => {
+                self.selection = Some(desktop.path.to_string_lossy().to_string());
+            }
+            SelectAction::PrintExec => {
+                self.selection = Some(desktop.exec.clone());
+            }
+        }
+    }
+
+    /// Ask the compositor for an xdg-activation token, if it supports that protocol.
+    ///
+    /// The token arrives later, as [Message::ActivationTokenDone]. In case it never does,
+    /// [Message::ActivationTokenTimedOut] follows after [ACTIVATION_TOKEN_TIMEOUT].
+    fn request_activation_token(&self) -> Option<AsyncRequestSerial> {
+        let serial = self
+            .main_window
+            .window()
+            .with_winit_window(|window| window.request_activation_token().ok())
+            .flatten()?;
+
+        let sender = self.sender.clone();
+        self.sender.spawn(async move {
+            smol::Timer::after(ACTIVATION_TOKEN_TIMEOUT).await;
+            sender
+                .send_async(Message::ActivationTokenTimedOut(serial))
+                .await;
+        });
+
+        Some(serial)
+    }
+
+    /// Launch the application waiting for the activation token requested with `serial`, and close.
+    fn launch_pending(&mut self, serial: AsyncRequestSerial, token: Option<&str>) {
+        // the launch may have happened already, if the token arrived just as we gave up on it.
+        let Some(PendingLaunch {
+            desktop, wrapper, ..
+        }) = self
+            .pending_launch
+            .take_if(|pending| pending.serial == serial)
+        else {
+            return;
+        };
+
+        self.start_launch(&desktop, wrapper.as_deref(), token);
+        self.finish();
+    }
+
+    /// Launch an application, and find out whether that worked: [Message::Launched] or
+    /// [Message::LaunchFailed] follows.
+    fn start_launch(
+        &mut self,
+        desktop: &DesktopEntry,
+        wrapper: Option<&Path>,
+        token: Option<&str>,
+    ) {
+        // until we hear back, the launcher must not close.
+        self.launching = true;
+
+        let file = self
+            .open_with
+            .as_ref()
+            .map(|open_with| open_with.file.as_path());
+        let apply_env = self.settings.apply_exec_env;
+        // read on every launch, so that changes apply without restarting the daemon
+        let env = crate::config::Options::load()
+            .launch_env
+            .for_app(&desktop.id);
+        let mut errors = match launch(desktop, wrapper, file, apply_env, env, token) {
+            Ok(errors) => errors,
+            Err(e) => {
+                log::error!("failed to launch: {e}");
+                let error = format!("Couldn't launch {}: {e}", desktop.name);
+                self.sender.send_or_queue(Message::LaunchFailed(error));
+                return;
+            }
+        };
+
+        let name = desktop.name.clone();
+        let sender = self.sender.clone();
+        self.sender.spawn(async move {
+            let error = smol::unblock(move || {
+                let mut error = String::new();
+                errors.read_to_string(&mut error).map(|_| error)
+            })
+            .await;
+
+            let message = match error {
+                Ok(error) if !error.is_empty() => {
+                    Message::LaunchFailed(format!("Couldn't launch {name}: {error}"))
+                }
+                // nothing to read: the application is running.
+                _ => Message::Launched,
+            };
+            sender.send_async(message).await;
+        });
+    }
+
+    /// Show an error to the user for `duration`.
+    fn show_error(&self, error: String, duration: Duration) {
+        self.main_window.set_error_message(error.as_str().into());
+
+        let sender = self.sender.clone();
+        self.sender.spawn(async move {
+            smol::Timer::after(duration).await;
+            sender.send_async(Message::ErrorExpired(error)).await;
+        });
+    }
+
+    /// Close, unless an application is still being launched; it closes once that succeeds.
+    ///
+    /// The window animates out first. Its result is only reported once it has closed, so how long
+    /// that takes is bounded by the maximum animation duration, never by the animation itself.
+    fn finish(&mut self) {
+        if self.pending_launch.is_some() || self.launching || self.closing {
+            return;
+        }
+
+        let duration = Duration::from_millis(self.settings.animation_duration_ms.into());
+        if duration.is_zero() {
+            self.sender.finish();
+            return;
+        }
+
+        self.closing = true;
+        self.main_window.set_shown(false);
+
+        let sender = self.sender.clone();
+        self.sender.spawn(async move {
+            smol::Timer::after(duration).await;
+            sender.finish();
+        });
+    }
+
+    fn select_man_page(&mut self, page: &ManPage) {
+        match self.select_action {
+            SelectAction::Launch => {
+                if let Err(e) = open_man_page(page) {
+                    log::error!("failed to open {}: {e}", page.command_line());
+                }
+            }
+            SelectAction::PrintPath | SelectAction::PrintExec => {
+                self.selection = Some(page.command_line());
+            }
+        }
+    }
+
+    fn run_command(&mut self, command: LauncherCommand) {
+        log::info!("running command {command:?}");
+
+        match command {
+            LauncherCommand::ReloadSettings => {
+                self.settings = read_settings();
+                self.apply_settings();
+            }
+            LauncherCommand::ClearHistory => {
+                self.bias = LaunchHistory::default();
+                if let Err(e) = Self::write_state(&self.bias) {
+                    log::error!("couldn't write launcher bias (scoring): {e}");
+                }
+            }
+            LauncherCommand::QuitDaemon => {
+                // quitting stops all apps, so this one doesn't need to finish itself
+                self.closing = true;
+                self.sender.shutdown_polymodo();
+            }
+        }
+    }
+
     fn apply_settings(&mut self) {
-        let LauncherSettings { transparency } = self.settings;
+        let LauncherSettings {
+            transparency,
+            show_commands,
+            match_exec,
+            wheel_moves_selection,
+            exec_weight,
+            max_icon_loads,
+            animation_duration_ms,
+            prefer_shorter_names,
+            hover_selects,
+            single_click_launches,
+            show_preview,
+            show_icons,
+            primary_label,
+            restore_query_secs,
+            search_debounce_ms,
+            max_query_len,
+            keep_warm,
+            apply_exec_env,
+            ..
+        } = self.settings;
         let window = &self.main_window;
 
         window.set_transparency(transparency);
+        window.set_show_commands(show_commands);
+        window.set_match_exec(match_exec);
+        window.set_wheel_moves_selection(wheel_moves_selection);
+        window.set_exec_weight(exec_weight);
+        window.set_max_icon_loads(max_icon_loads as i32);
+        window.set_animation_duration_ms(animation_duration_ms as i32);
+        window.set_prefer_shorter_names(prefer_shorter_names);
+        window.set_hover_selects(hover_selects);
+        window.set_single_click_launches(single_click_launches);
+        window.set_show_preview(show_preview);
+        window.set_show_icons(show_icons);
+        window.set_generic_names_first(primary_label == PrimaryLabel::GenericName);
+        window.set_restore_query_secs(restore_query_secs as i32);
+        window.set_search_debounce_ms(search_debounce_ms as i32);
+        window.set_max_query_len(max_query_len as i32);
+        window.set_keep_warm(keep_warm);
+        window.set_apply_exec_env(apply_exec_env);
+        window.set_icon_theme(
+            self.settings
+                .icon_theme
+                .as_deref()
+                .unwrap_or_default()
+                .into(),
+        );
+        self.filter.show_commands.set(show_commands);
+        self.order.prefer_shorter_names.set(prefer_shorter_names);
+        self.primary_label.set(primary_label);
+        self.search_debounce
+            .set(Duration::from_millis(search_debounce_ms.into()));
+
+        let highlight_color = &self.settings.highlight_color;
+        let highlight_text_color = &self.settings.highlight_text_color;
+        window.set_highlight_color(highlight_color.as_str().into());
+        window.set_highlight_text_color(highlight_text_color.as_deref().unwrap_or_default().into());
+
+        // both colors have been validated when the settings were sanitized
+        let entries = window.global::<ui::LauncherEntries>();
+        entries.set_show_icons(show_icons);
+        entries.set_highlight_color(parse_color(highlight_color).unwrap_or_default());
+        entries.set_highlight_text_color(
+            highlight_text_color
+                .as_deref()
+                .and_then(parse_color)
+                .unwrap_or_default(),
+        );
+    }
+}
+
+/// How often icons were already cached when an entry was added, for diagnostics.
+#[derive(Debug, Default, Copy, Clone)]
+struct IconCacheStats {
+    hits: u32,
+    misses: u32,
+}
+
+/// Decides which entries are shown in the UI.
+#[derive(Default)]
+struct EntryFilter {
+    show_hidden: Cell<bool>,
+    show_commands: Cell<bool>,
+    /// Whether polymodo's commands make sense for what the selected entry is used for
+    commands_apply: Cell<bool>,
+    /// Only accept applications that can open files of this MIME type, if set
+    mime_type: RefCell<Option<String>>,
+}
+
+impl EntryFilter {
+    fn accepts(&self, entry: &LauncherEntry) -> bool {
+        let is_command = matches!(entry.kind, EntryKind::Command(_));
+        let opens_file = match &*self.mime_type.borrow() {
+            Some(mime_type) => entry
+                .desktop()
+                .is_some_and(|desktop| mime::can_open(&desktop.mime_types, mime_type)),
+            None => true,
+        };
+
+        entry.shown
+            && opens_file
+            && (!entry.hidden || self.show_hidden.get())
+            && (!is_command || (self.show_commands.get() && self.commands_apply.get()))
+    }
+}
+
+/// Decides the order entries are shown in, best first.
+///
+/// Entries are ranked by, in order of precedence:
+/// 1. whether they are the `preferred` application, i.e. the default for a file being opened,
+/// 2. their frecency bias,
+/// 3. their fuzzy score,
+/// 4. when they were last launched, most recent first,
+/// 5. the length of their name, shortest first, if `prefer_shorter_names` is set.
+///
+/// Later criteria only break ties in earlier ones. Recency comes before name length because it
+/// says something about this user, where name length is only a guess.
+#[derive(Default)]
+struct EntryOrder {
+    prefer_shorter_names: Cell<bool>,
+    /// The desktop file id of the application to show first, if any
+    preferred: RefCell<Option<String>>,
+}
+
+impl EntryOrder {
+    /// Compare two entries, where the better entry is [Ordering::Greater].
+    fn compare(&self, a: &LauncherEntry, b: &LauncherEntry) -> Ordering {
+        let preferred = self.preferred.borrow();
+        let is_preferred = |entry: &LauncherEntry| {
+            entry
+                .desktop()
+                .is_some_and(|desktop| Some(&desktop.id) == preferred.as_ref())
+        };
+
+        is_preferred(a)
+            .cmp(&is_preferred(b))
+            .then_with(|| {
+                (a.bias, a.score)
+                    .partial_cmp(&(b.bias, b.score))
+                    .unwrap_or(Ordering::Equal)
+            })
+            // never launched (`None`) sorts before any launch.
+            .then_with(|| a.last_launched.cmp(&b.last_launched))
+            .then_with(|| {
+                if self.prefer_shorter_names.get() {
+                    // shorter is better, so reversed
+                    b.name().chars().count().cmp(&a.name().chars().count())
+                } else {
+                    Ordering::Equal
+                }
+            })
     }
 }
 
 #[derive(Default, Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct EntryId(pub usize);
 
+/// A string an entry may be found by. One entry may have many of these.
 pub struct SearchEntry {
     for_id: EntryId,
+    column: SearchColumn,
     text: SharedString,
 }
 
+/// What part of an entry a [SearchEntry] matches on.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum SearchColumn {
+    Name,
+    /// Only matched on when applications are labelled by it, see [PrimaryLabel]
+    GenericName,
+    /// The file name of the executed program
+    Exec,
+    /// The beginnings of the words of the name, see [matches_initials]. These aren't pushed to the
+    /// matcher, but checked against the names it matched.
+    Initials,
+}
+
+impl SearchColumn {
+    /// How much a match on this column counts, relative to other columns.
+    /// A weight of zero means matches on this column are ignored.
+    ///
+    /// For [SearchColumn::Initials], this is the share of the best match's score that names
+    /// matching by their initials are lifted to, see [score_matches].
+    fn weight(self, settings: &LauncherSettings) -> f32 {
+        match self {
+            SearchColumn::Name => 1.0,
+            SearchColumn::GenericName if settings.primary_label == PrimaryLabel::GenericName => 1.0,
+            SearchColumn::GenericName => 0.0,
+            SearchColumn::Exec if settings.match_exec => settings.exec_weight,
+            SearchColumn::Exec => 0.0,
+            SearchColumn::Initials => 0.5,
+        }
+    }
+}
+
+/// The row `anchor` has to be selected in again, if it was selected before the entries changed,
+/// and isn't anymore. `rows` are the ids of the entries as shown, and are only looked through if
+/// the selection did move: most changes happen elsewhere in the list.
+fn row_to_reselect(
+    anchor: EntryId,
+    selected: Option<EntryId>,
+    rows: impl IntoIterator<Item = EntryId>,
+) -> Option<usize> {
+    if selected == Some(anchor) {
+        return None;
+    }
+
+    rows.into_iter().position(|id| id == anchor)
+}
+
+/// Score each matched entry by its best match: the matcher's score for it, weighed by the column
+/// it matched on. Entries that only matched on ignored columns get no score.
+///
+/// Names whose words start with `query`, like GNOME Chess for `gch`, always match fuzzily too, if
+/// not always well: their initials lift them up to a share of the best score, so that they show
+/// up near the top, but never above a good fuzzy match.
+fn score_matches(
+    query: &str,
+    matches: &[(u32, &SearchEntry)],
+    settings: &LauncherSettings,
+) -> HashMap<EntryId, f32> {
+    let mut scores: HashMap<EntryId, f32> = HashMap::new();
+    for (score, entry) in matches {
+        let weight = entry.column.weight(settings);
+        if weight <= 0.0 {
+            continue;
+        }
+
+        let score = weight * *score as f32;
+        let best = scores.entry(entry.for_id).or_insert(score);
+        *best = best.max(score);
+    }
+
+    let best = scores.values().copied().fold(0.0, f32::max);
+    let lifted = SearchColumn::Initials.weight(settings) * best;
+    for (_, entry) in matches
+        .iter()
+        .filter(|(_, entry)| entry.column == SearchColumn::Name)
+    {
+        if matches_initials(query, &entry.text) {
+            let score = scores.entry(entry.for_id).or_default();
+            *score = score.max(lifted);
+        }
+    }
+
+    scores
+}
+
+/// Whether `query` spells the beginnings of the words of `name`, in order, starting at its first
+/// word: `gc` and `gch` both match "GNOME Chess". Words are also split where a capital follows a
+/// lowercase letter, as in "LibreOffice". Queries of a single letter match nothing, as the
+/// matcher already prefers names that start with them.
+fn matches_initials(query: &str, name: &str) -> bool {
+    fn spells(query: &[char], words: &[Vec<char>]) -> bool {
+        let Some((word, rest)) = words.split_first() else {
+            return query.is_empty();
+        };
+        if query.is_empty() {
+            return true;
+        }
+
+        // take one or more letters from the start of this word, and the rest from the next ones
+        (1..=query.len().min(word.len()))
+            .take_while(|&len| query[len - 1] == word[len - 1])
+            .any(|len| spells(&query[len..], rest))
+    }
+
+    let query = query.to_lowercase().chars().collect::<Vec<_>>();
+    if query.len() < 2 {
+        return false;
+    }
+
+    let mut words: Vec<Vec<char>> = Vec::new();
+    let mut previous = ' ';
+    for c in name.chars() {
+        if c.is_alphanumeric() {
+            let new_word =
+                !previous.is_alphanumeric() || (c.is_uppercase() && previous.is_lowercase());
+            if new_word {
+                words.push(Vec::new());
+            }
+            if let Some(word) = words.last_mut() {
+                word.extend(c.to_lowercase());
+            }
+        }
+        previous = c;
+    }
+
+    spells(&query, &words)
+}
+
 impl crate::fuzzy_search::Row<1> for SearchEntry {
     type Output = String;
 
@@ -289,49 +2152,217 @@ pub struct LauncherEntry {
     id: EntryId,
     /// Whether this entry should be shown in the UI
     shown: bool,
-    /// The score this entry got from the fuzzy matcher
-    score: u32,
-    /// The desktop entry this corresponds with
-    desktop: Arc<DesktopEntry>,
+    /// The score this entry got from the fuzzy matcher, weighed by the column it matched on
+    score: f32,
+    /// The frecency bias of this entry, from the launch history
+    bias: f32,
+    /// When this entry was last launched, from the launch history
+    last_launched: Option<SystemTime>,
+    /// Whether the user has hidden this entry
+    hidden: bool,
+    /// What this entry corresponds with
+    kind: EntryKind,
     /// This entry's rendered icon
     icon: Option<Pixels>,
 }
 
+#[derive(Debug, Clone)]
+pub enum EntryKind {
+    /// An application, described by a desktop entry
+    Desktop(Arc<DesktopEntry>),
+    /// One of polymodo's own commands
+    Command(LauncherCommand),
+    /// A manual page, only searched for with [MAN_PREFIX]
+    ManPage(Arc<ManPage>),
+}
+
 impl LauncherEntry {
-    pub fn to_slint(&self) -> ui::LauncherEntry {
+    pub fn name(&self) -> SharedString {
+        match &self.kind {
+            EntryKind::Desktop(desktop) => desktop.name.clone(),
+            EntryKind::Command(command) => command.name().into(),
+            EntryKind::ManPage(page) => page.name.as_str().into(),
+        }
+    }
+
+    /// The frames of this entry's icon, if it is shown and animated.
+    fn icon_animation(&self) -> Option<IconFrames> {
+        self.icon.as_ref()?;
+
+        icon_animation(self.desktop()?.icon.as_deref()?)
+    }
+
+    /// The WM class of the windows this entry opens, if it is an application that declares one.
+    #[expect(unused)] // for matching windows to entries, once polymodo can see them
+    pub fn startup_wm_class(&self) -> Option<&str> {
+        self.desktop()?.startup_wm_class.as_deref()
+    }
+
+    pub fn desktop(&self) -> Option<&Arc<DesktopEntry>> {
+        match &self.kind {
+            EntryKind::Desktop(desktop) => Some(desktop),
+            EntryKind::Command(_) | EntryKind::ManPage(_) => None,
+        }
+    }
+
+    /// Convert to the slint struct that represents this entry, labelled by `primary_label`.
+    pub fn to_slint(&self, primary_label: PrimaryLabel) -> ui::LauncherEntry {
         let icon = self
             .icon
             .as_ref()
             .map(|buffer| slint::Image::from_rgba8(buffer.clone()))
             .unwrap_or_default();
 
+        let (generic_name, description) = match &self.kind {
+            EntryKind::Desktop(desktop) => (
+                desktop.generic_name.clone().unwrap_or_default(),
+                desktop.description.clone().unwrap_or_default(),
+            ),
+            EntryKind::Command(command) => ("polymodo".into(), command.description().into()),
+            EntryKind::ManPage(page) => (
+                format!("man {}", page.section).into(),
+                page.description.as_str().into(),
+            ),
+        };
+
+        // entries without a generic name keep being labelled by their name
+        let (name, generic_name) = match primary_label {
+            PrimaryLabel::GenericName if self.desktop().is_some() && !generic_name.is_empty() => {
+                (generic_name, self.name())
+            }
+            PrimaryLabel::Name | PrimaryLabel::GenericName => (self.name(), generic_name),
+        };
+
         ui::LauncherEntry {
-            name: self.desktop.name.clone(),
-            generic_name: self.desktop.generic_name.clone().unwrap_or_default(),
-            description: self.desktop.description.clone().unwrap_or_default(),
+            name,
+            generic_name,
+            description,
             icon,
             id: self.id.0 as i32,
+            score: self.score,
+            bias: self.bias,
+            last_launch_age: self
+                .last_launched
+                .and_then(|time| time.elapsed().ok())
+                .map(format_age)
+                .unwrap_or_else(|| "never".to_string())
+                .into(),
+            hidden: self.hidden,
+            modified: self
+                .desktop()
+                .is_some_and(|desktop| desktop.overrides.is_some()),
         }
     }
 }
 
-fn launch(desktop: &DesktopEntry) -> anyhow::Result<()> {
+/// Format a duration coarsely, like `5m ago`.
+pub(super) fn format_age(age: Duration) -> String {
+    const MINUTE: u64 = 60;
+    const HOUR: u64 = 60 * MINUTE;
+    const DAY: u64 = 24 * HOUR;
+
+    match age.as_secs() {
+        secs if secs < MINUTE => format!("{secs}s ago"),
+        secs if secs < HOUR => format!("{}m ago", secs / MINUTE),
+        secs if secs < DAY => format!("{}h ago", secs / HOUR),
+        secs => format!("{}d ago", secs / DAY),
+    }
+}
+
+/// `query`, cut off after `max_chars` characters, or `None` if it isn't that long.
+///
+/// Cuts at a character boundary, so that multi-byte characters are never split.
+fn truncate_query(query: &str, max_chars: usize) -> Option<&str> {
+    let (end, _) = query.char_indices().nth(max_chars)?;
+
+    Some(&query[..end])
+}
+
+/// Find an executable called `name` in `$PATH`.
+pub(super) fn find_program(name: &str) -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(name))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Put `text` on the clipboard, through `wl-copy`.
+fn copy_to_clipboard(text: &str) -> anyhow::Result<()> {
+    let wl_copy = find_program("wl-copy").ok_or_else(|| anyhow!("wl-copy is not installed"))?;
+
+    // wl-copy serves the clipboard from a process of its own, and exits right away
+    spawn_detached(Command::new(wl_copy).arg("--").arg(text))?;
+
+    Ok(())
+}
+
+/// Read the launcher's settings from their config file, or from where they were kept before
+/// there was one.
+fn read_settings() -> LauncherSettings {
+    Launcher::read_config::<LauncherSettings>()
+        .or_else(|_| Launcher::read_state::<LegacyLauncherSettings>().map(LauncherSettings::from))
+        .unwrap_or_default()
+        .sanitize()
+}
+
+/// How long to wait for the compositor to hand out an activation token before launching without.
+const ACTIVATION_TOKEN_TIMEOUT: Duration = Duration::from_millis(200);
+/// How long errors are shown for, usually.
+pub(super) const ERROR_DURATION: Duration = Duration::from_secs(3);
+
+/// Launch the application described by `desktop`. If a `wrapper` is given, it is launched instead,
+/// with the application's command line as its arguments.
+///
+/// If given, the application is asked to open `file`.
+///
+/// The application is launched with `env` set, as configured in [crate::config::LaunchEnv]. The
+/// entry's own variables take precedence. With `apply_env`, a leading `env KEY=VALUE...` in the
+/// entry's `Exec` is applied to the environment as well, instead of launched, see
+/// [split_env_prefix].
+///
+/// If given, the `activation_token` lets the application's window take focus when it opens.
+///
+/// Returns a pipe that is closed once the application is running, or that yields the reason it
+/// couldn't be started.
+fn launch(
+    desktop: &DesktopEntry,
+    wrapper: Option<&Path>,
+    file: Option<&Path>,
+    apply_env: bool,
+    env: IndexMap<String, String>,
+    activation_token: Option<&str>,
+) -> anyhow::Result<PipeReader> {
+    // field codes are replaced with strings, which a path that isn't UTF-8 can't be
+    let file = file
+        .map(|file| {
+            file.to_str()
+                .ok_or_else(|| anyhow!("{} is not a UTF-8 path", file.display()))
+        })
+        .transpose()?;
+    let uri = file.map(file_uri);
+
+    // both ends are closed on exec, so a successful launch is seen as the pipe closing.
+    let (errors, mut error_writer) = std::io::pipe()?;
+
     match fork::fork().map_err(|_| anyhow!("failed to fork process"))? {
         fork::Fork::Child => {
+            drop(errors);
+
             // detach
             if let Err(e) = nix::unistd::daemon(false, false) {
                 log::error!("daemonize failed: {}", e);
             }
 
-            // %f and %F: lists of files. polymodo does not yet support selecting files.
-            let exec = desktop.exec.replace("%f", "").replace("%F", "");
-            // same story for %u and %U:
-            let exec = exec.replace("%u", "").replace("%U", "");
-
             // split exec by spaces
-            let mut args = exec
+            let mut args = desktop
+                .exec
                 .split(" ")
                 .flat_map(|arg| match arg {
+                    // %f: a file, %u: a URL. %F and %U are lists of those, of which polymodo only
+                    // ever has one.
+                    "%f" | "%F" => Vec::from_iter(file),
+                    "%u" | "%U" => Vec::from_iter(uri.as_deref()),
                     "%i" => vec!["--icon", desktop.icon.as_deref().unwrap_or("")],
                     "%c" => vec![desktop.name.as_str()],
                     "%k" => {
@@ -345,23 +2376,257 @@ fn launch(desktop: &DesktopEntry) -> anyhow::Result<()> {
                     _ => vec![arg],
                 })
                 .collect::<Vec<_>>();
+            let exec_env = match apply_env {
+                true => split_env_prefix(&mut args),
+                false => vec![],
+            };
             // the first "argument" is the program to launch
             let program = args.remove(0);
 
             log::debug!("launching: prog='{}' args='{}'", program, args.join(" "));
 
-            let error = Command::new(program).args(args).exec(); // this will never return if the exec succeeds
+            let mut command = match wrapper {
+                Some(wrapper) => {
+                    let mut command = Command::new(wrapper);
+                    command.arg(program);
+                    command
+                }
+                None => Command::new(program),
+            };
+
+            if !env.is_empty() {
+                log::debug!("launching with configured environment {env:?}");
+            }
+
+            command
+                .args(args)
+                .envs(env)
+                .envs(desktop.env.iter().map(|(k, v)| (k, v)))
+                .envs(exec_env);
+
+            if let Some(token) = activation_token {
+                // DESKTOP_STARTUP_ID is the X11 name for it, which some toolkits still look for
+                command
+                    .env("XDG_ACTIVATION_TOKEN", token)
+                    .env("DESKTOP_STARTUP_ID", token);
+            }
+
+            let error = command.exec(); // this will never return if the exec succeeds
 
-            // but if it did return, log the error and return:
+            // but if it did return, log the error, tell the launcher, and return:
             log::error!("failed to launch: {}", error);
+            let _ = write!(error_writer, "{error}");
             let _ = std::io::stdout().flush();
             std::process::exit(-1);
         }
         fork::Fork::Parent(pid) => {
             log::info!("Launching {:?} with pid {pid}", desktop.name.as_str());
+            drop(error_writer);
 
             let _ = std::io::stdout().flush();
-            Ok(())
+            Ok(errors)
+        }
+    }
+}
+
+/// The `file://` URI of the absolute `path`, with everything but unreserved characters and
+/// slashes percent-encoded.
+fn file_uri(path: &str) -> String {
+    let mut uri = String::from("file://");
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{byte:02X}")),
+        }
+    }
+
+    uri
+}
+
+/// If `args` start with `env` followed by `KEY=VALUE` assignments, remove those and return the
+/// assignments, so that they can be set in the environment of the program that follows instead.
+///
+/// `env` is left alone if anything else follows it first (like an option), or if no program
+/// follows the assignments: then it's not clear what a shell would do.
+pub(super) fn split_env_prefix<'a>(args: &mut Vec<&'a str>) -> Vec<(&'a str, &'a str)> {
+    if args.first() != Some(&"env") {
+        return vec![];
+    }
+
+    let assignments = args[1..]
+        .iter()
+        .map_while(|arg| {
+            arg.split_once('=')
+                .filter(|(key, _)| !key.is_empty() && !key.starts_with('-'))
+        })
+        .collect::<Vec<_>>();
+    if assignments.is_empty() || assignments.len() + 1 == args.len() {
+        return vec![];
+    }
+
+    args.drain(..=assignments.len());
+    assignments
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn env_prefix_is_split_off() {
+        let mut args = vec!["env", "GDK_BACKEND=x11", "LANG=", "gimp", "A=b"];
+        assert_eq!(
+            split_env_prefix(&mut args),
+            vec![("GDK_BACKEND", "x11"), ("LANG", "")]
+        );
+        assert_eq!(args, vec!["gimp", "A=b"]);
+
+        // only a leading `env` counts
+        let mut args = vec!["gimp", "A=b"];
+        assert!(split_env_prefix(&mut args).is_empty());
+        assert_eq!(args, vec!["gimp", "A=b"]);
+
+        // options to env itself, or nothing to run: leave it to env
+        for original in [vec!["env", "-u", "A", "gimp"], vec!["env", "A=b"]] {
+            let mut args = original.clone();
+            assert!(split_env_prefix(&mut args).is_empty());
+            assert_eq!(args, original);
         }
     }
+
+    #[test]
+    fn files_are_passed_as_uris() {
+        assert_eq!(
+            file_uri("/home/user/Documents/notes.txt"),
+            "file:///home/user/Documents/notes.txt"
+        );
+        assert_eq!(
+            file_uri("/tmp/50% off#1 ünïcode.pdf"),
+            "file:///tmp/50%25%20off%231%20%C3%BCn%C3%AFcode.pdf"
+        );
+    }
+
+    #[test]
+    fn initials_are_matched() {
+        assert!(matches_initials("gc", "GNOME Chess"));
+        assert!(matches_initials("gch", "GNOME Chess"));
+        assert!(matches_initials("GCh", "GNOME Chess"));
+        assert!(matches_initials("low", "LibreOffice Writer"));
+        assert!(matches_initials("vsc", "Visual Studio Code"));
+
+        // letters that aren't at the start of a word, or skip one
+        assert!(!matches_initials("gs", "GNOME Chess"));
+        assert!(!matches_initials("ch", "GNOME Chess"));
+        assert!(!matches_initials("vc", "Visual Studio Code"));
+        assert!(!matches_initials("lw", "LibreOffice Writer"));
+        // a single letter matches nothing
+        assert!(!matches_initials("g", "GNOME Chess"));
+    }
+
+    #[test]
+    fn selection_is_kept_on_its_entry() {
+        let rows = || [3, 1, 4, 5].map(EntryId);
+        let untouched = || std::iter::from_fn(|| panic!("rows were looked through"));
+
+        // an entry was added above the selected one
+        assert_eq!(
+            row_to_reselect(EntryId(4), Some(EntryId(1)), rows()),
+            Some(2)
+        );
+        // it is still selected: nothing to look for
+        assert_eq!(
+            row_to_reselect(EntryId(4), Some(EntryId(4)), untouched()),
+            None
+        );
+        // the selected entry isn't shown anymore
+        assert_eq!(row_to_reselect(EntryId(9), Some(EntryId(1)), rows()), None);
+    }
+
+    fn search_entry(id: usize, column: SearchColumn, text: &str) -> SearchEntry {
+        SearchEntry {
+            for_id: EntryId(id),
+            column,
+            text: text.into(),
+        }
+    }
+
+    #[test]
+    fn matches_are_scored_by_weight() {
+        let settings = LauncherSettings::default();
+        let name = search_entry(0, SearchColumn::Name, "Firefox");
+        let exec = search_entry(0, SearchColumn::Exec, "firefox");
+        let other_exec = search_entry(1, SearchColumn::Exec, "firefox-esr");
+        let generic_name = search_entry(2, SearchColumn::GenericName, "Web Browser");
+
+        let scores = score_matches(
+            "fire",
+            &[
+                (100, &name),
+                (60, &exec),
+                (120, &other_exec),
+                (150, &generic_name),
+            ],
+            &settings,
+        );
+
+        // an entry keeps its best match, and exec matches count for half
+        assert_eq!(scores[&EntryId(0)], 100.0);
+        assert_eq!(scores[&EntryId(1)], 60.0);
+        // generic names aren't matched on unless they're shown
+        assert!(!scores.contains_key(&EntryId(2)));
+    }
+
+    #[test]
+    fn initials_dont_outrank_good_matches() {
+        let settings = LauncherSettings::default();
+        let calculator = search_entry(0, SearchColumn::Name, "gcalctool");
+        let chess = search_entry(1, SearchColumn::Name, "GNOME Chess");
+        let charmap = search_entry(2, SearchColumn::Name, "gucharmap");
+
+        // a poor fuzzy match is lifted by its initials, but only up to half the best match
+        let scores = score_matches("gc", &[(200, &calculator), (20, &chess)], &settings);
+        assert_eq!(scores[&EntryId(1)], 100.0);
+
+        // and never lowered
+        let scores = score_matches(
+            "gc",
+            &[(200, &calculator), (150, &chess), (120, &charmap)],
+            &settings,
+        );
+        assert_eq!(scores[&EntryId(1)], 150.0);
+        assert!(scores[&EntryId(1)] < scores[&EntryId(0)]);
+        assert!(scores[&EntryId(1)] > scores[&EntryId(2)]);
+    }
+
+    #[test]
+    fn better_matches_rank_higher() {
+        let mut search: FuzzySearch<1, SearchEntry> = FuzzySearch::create_with_config({
+            let mut config = nucleo::Config::DEFAULT;
+            config.prefer_prefix = true;
+            config
+        });
+        search.push_all([
+            search_entry(0, SearchColumn::Name, "Neovim"),
+            search_entry(1, SearchColumn::Name, "Text Editor"),
+            search_entry(1, SearchColumn::Exec, "vim"),
+            search_entry(2, SearchColumn::Name, "Vim"),
+        ]);
+
+        search.search::<0>("vim");
+        search.finish();
+
+        let scores = score_matches(
+            "vim",
+            &search.get_scored_matches(),
+            &LauncherSettings::default(),
+        );
+        let mut ranked = scores.into_iter().collect::<Vec<_>>();
+        ranked.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+        let ranked = ranked.into_iter().map(|(id, _)| id.0).collect::<Vec<_>>();
+
+        // the name matches exactly, the exec does too but weighs less
+        assert_eq!(ranked, [2, 0, 1]);
+    }
 }
@@ -0,0 +1,133 @@
+use super::entry::next_id;
+use super::launcher::{find_program, Message, ERROR_DURATION};
+use crate::app::AppSender;
+use crate::mode::spawn_detached;
+use std::process::Command;
+use std::sync::Arc;
+
+/// Queries starting with this search manual pages, instead of applications.
+pub const MAN_PREFIX: &str = "man:";
+
+/// A manual page, as listed by `man -k`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManPage {
+    pub name: String,
+    pub section: String,
+    pub description: String,
+}
+
+impl ManPage {
+    /// The command line that shows this page, like `man 1 grep`.
+    pub fn command_line(&self) -> String {
+        format!("man {} {}", self.section, self.name)
+    }
+}
+
+/// If `query` searches manual pages, the part of it that is the actual query.
+pub fn man_query(query: &str) -> Option<&str> {
+    query.strip_prefix(MAN_PREFIX).map(str::trim_start)
+}
+
+/// Find all manual pages on this system, pushing them to the launcher as they are found.
+///
+/// This blocks for as long as `man -k` takes, which can be a while on a cold cache.
+pub fn scour_man_pages(sender: AppSender<Message>) {
+    sender.set_busy(true);
+
+    // `man -k .` lists every page whose name or description matches `.`, i.e. all of them.
+    match Command::new("man").args(["-k", "."]).output() {
+        Ok(output) => {
+            let output = String::from_utf8_lossy(&output.stdout);
+            let mut count = 0;
+
+            for page in output.lines().filter_map(parse_apropos_line) {
+                sender.send_blocking(Message::NewManPage(next_id(), Arc::new(page)));
+                count += 1;
+            }
+
+            log::debug!("found {count} manual pages");
+        }
+        Err(e) => {
+            log::error!("couldn't list manual pages: {e}");
+            // otherwise, searching them would just never find anything
+            let error = format!("Couldn't list manual pages: {e}");
+            sender.send_blocking(Message::ShowError(error, ERROR_DURATION));
+        }
+    }
+
+    sender.set_busy(false);
+}
+
+/// Parse a line of `man -k` output, like `grep (1)             - print lines that match patterns`.
+fn parse_apropos_line(line: &str) -> Option<ManPage> {
+    let (page, description) = line.split_once(" - ")?;
+    let (name, section) = page.trim().split_once(" (")?;
+    let section = section.strip_suffix(')')?;
+
+    Some(ManPage {
+        name: name.trim().to_string(),
+        section: section.to_string(),
+        description: description.trim().to_string(),
+    })
+}
+
+/// Show a manual page in a new terminal.
+///
+/// `xdg-terminal-exec` is preferred; otherwise, the terminal in `$TERMINAL` is started with `-e`,
+/// which just about every terminal understands.
+pub fn open_man_page(page: &ManPage) -> anyhow::Result<()> {
+    let man = ["man", page.section.as_str(), page.name.as_str()];
+
+    let mut command = if let Some(terminal_exec) = find_program("xdg-terminal-exec") {
+        let mut command = Command::new(terminal_exec);
+        command.args(man);
+        command
+    } else if let Some(terminal) = std::env::var_os("TERMINAL") {
+        let mut command = Command::new(terminal);
+        command.arg("-e").args(man);
+        command
+    } else {
+        anyhow::bail!("no terminal found: install xdg-terminal-exec, or set $TERMINAL");
+    };
+
+    let pid = spawn_detached(&mut command)?;
+    log::info!("Opening {} with pid {pid}", page.command_line());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn apropos_lines_are_parsed() {
+        let page = |name: &str, section: &str, description: &str| ManPage {
+            name: name.to_string(),
+            section: section.to_string(),
+            description: description.to_string(),
+        };
+
+        assert_eq!(
+            parse_apropos_line("grep (1)             - print lines that match patterns"),
+            Some(page("grep", "1", "print lines that match patterns"))
+        );
+        // sections can have a suffix, and descriptions can contain the separator
+        assert_eq!(
+            parse_apropos_line("open (3p)            - open a file - POSIX"),
+            Some(page("open", "3p", "open a file - POSIX"))
+        );
+        assert_eq!(
+            parse_apropos_line("systemd.unit (5) - Unit configuration"),
+            Some(page("systemd.unit", "5", "Unit configuration"))
+        );
+
+        // like the `nothing appropriate.` man prints when nothing matches
+        assert_eq!(parse_apropos_line("foo: nothing appropriate."), None);
+        assert_eq!(
+            parse_apropos_line("grep - print lines that match patterns"),
+            None
+        );
+        assert_eq!(parse_apropos_line(""), None);
+    }
+}
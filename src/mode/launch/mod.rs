@@ -1,6 +1,11 @@
+mod command;
 mod entry;
 mod history;
 mod launcher;
+mod man;
 mod settings;
+mod stats;
 
+pub use entry::{register_caches, watch_system_icon_theme};
 pub use launcher::*;
+pub use stats::LaunchStats;
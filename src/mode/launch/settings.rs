@@ -1,13 +1,102 @@
 use crate::persistence::StorableState;
+use std::collections::HashSet;
+use std::path::PathBuf;
 
-#[derive(Debug, bincode::Decode, bincode::Encode)]
+/// The background of the selected entry, unless configured otherwise.
+pub const DEFAULT_HIGHLIGHT_COLOR: &str = "#ffffff1a";
+/// How many icons may be loaded at the same time, unless configured otherwise.
+pub const DEFAULT_MAX_ICON_LOADS: u32 = 8;
+/// More concurrent icon loads than this only starve the thread pool.
+const MAX_ICON_LOADS_LIMIT: u32 = 64;
+/// Closing waits for its animation, so it must not take long: scripts may be waiting on the result.
+const MAX_ANIMATION_DURATION_MS: u32 = 500;
+/// Searching waits this long for more typing to finish, so slow machines needn't search for what
+/// is typed after every character.
+const MAX_SEARCH_DEBOUNCE_MS: u32 = 500;
+/// How many characters a query may have, unless configured otherwise.
+pub const DEFAULT_MAX_QUERY_LEN: u32 = 1024;
+/// Queries are only ever this long by accident, and matching them makes the launcher stutter.
+const MAX_QUERY_LEN_LIMIT: u32 = 4096;
+
+/// The launcher's settings, kept in a config file that may be edited by hand: see
+/// [crate::persistence::read_config]. Settings missing from it keep their default.
+///
+/// They used to be kept as bincode state, which is still read if there is no config file yet: see
+/// [LegacyLauncherSettings].
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 pub struct LauncherSettings {
     pub transparency: f32,
+    /// Desktop entries (by path) the user has chosen to hide from the launcher.
+    pub hidden_entries: HashSet<PathBuf>,
+    /// Whether polymodo's own commands show up in search results.
+    pub show_commands: bool,
+    /// Whether entries may also be found by the name of the program they execute.
+    pub match_exec: bool,
+    /// Whether scrolling moves the selected entry, rather than scrolling the list.
+    pub wheel_moves_selection: bool,
+    /// How much matching on the program name counts, relative to matching on the entry's name.
+    pub exec_weight: f32,
+    /// Whether entries with shorter names rank higher, if they are otherwise ranked equally.
+    pub prefer_shorter_names: bool,
+    /// Whether hovering over an entry selects it.
+    pub hover_selects: bool,
+    /// Whether entries launch with a single click, rather than a double click.
+    pub single_click_launches: bool,
+    /// Whether the details of the selected entry are shown beside the results.
+    pub show_preview: bool,
+    /// Which name of an entry is shown as its label.
+    pub primary_label: PrimaryLabel,
+    /// Whether entries have icons. Without, no icon is ever loaded, which makes for a faster
+    /// launcher on slow disks.
+    pub show_icons: bool,
+    /// The icon theme to look icons up in, if not the default one.
+    pub icon_theme: Option<String>,
+    /// For how long after closing the launcher its query is restored when it's opened again, in
+    /// seconds. 0 never restores it.
+    pub restore_query_secs: u32,
+    /// How long after the last typed character the query is searched for, in milliseconds. 0
+    /// searches after every character.
+    pub search_debounce_ms: u32,
+    /// How many characters a query may have; longer ones (e.g. accidental pastes) are truncated.
+    pub max_query_len: u32,
+    /// Whether the launcher is kept around, hidden, when it closes, so that it opens instantly
+    /// the next time. This costs memory for as long as it isn't shown.
+    pub keep_warm: bool,
+    /// Whether `KEY=VALUE` arguments after a leading `env` in an entry's `Exec` are set in the
+    /// environment, as a shell would, rather than passed to `env` as arguments.
+    pub apply_exec_env: bool,
+    /// The background color of the selected entry, as `#rrggbb[aa]`.
+    pub highlight_color: String,
+    /// The text color of the selected entry, as `#rrggbb[aa]`, if it should differ from the theme's.
+    pub highlight_text_color: Option<String>,
+    /// How many icons may be loaded at the same time.
+    pub max_icon_loads: u32,
+    /// How long the window animates when opening and closing, in milliseconds. 0 disables
+    /// animations, for those who prefer reduced motion.
+    pub animation_duration_ms: u32,
 }
 
 impl LauncherSettings {
     pub fn sanitize(mut self) -> Self {
         self.transparency = self.transparency.clamp(0.0, 1.0);
+        self.exec_weight = self.exec_weight.clamp(0.0, 1.0);
+        self.max_icon_loads = self.max_icon_loads.clamp(1, MAX_ICON_LOADS_LIMIT);
+        self.animation_duration_ms = self.animation_duration_ms.min(MAX_ANIMATION_DURATION_MS);
+        self.max_query_len = self.max_query_len.clamp(1, MAX_QUERY_LEN_LIMIT);
+        self.search_debounce_ms = self.search_debounce_ms.min(MAX_SEARCH_DEBOUNCE_MS);
+
+        if parse_color(&self.highlight_color).is_none() {
+            log::warn!("invalid highlight color {:?}", self.highlight_color);
+            self.highlight_color = DEFAULT_HIGHLIGHT_COLOR.to_string();
+        }
+
+        if let Some(color) = &self.highlight_text_color {
+            if parse_color(color).is_none() {
+                log::warn!("invalid highlight text color {color:?}");
+                self.highlight_text_color = None;
+            }
+        }
 
         self
     }
@@ -15,10 +104,100 @@ impl LauncherSettings {
 
 impl Default for LauncherSettings {
     fn default() -> Self {
-        Self { transparency: 0.2 }
+        Self {
+            transparency: 0.2,
+            hidden_entries: HashSet::new(),
+            show_commands: false,
+            match_exec: true,
+            wheel_moves_selection: false,
+            exec_weight: 0.5,
+            prefer_shorter_names: true,
+            hover_selects: false,
+            single_click_launches: false,
+            show_preview: false,
+            primary_label: PrimaryLabel::default(),
+            show_icons: true,
+            icon_theme: None,
+            restore_query_secs: 30,
+            search_debounce_ms: 50,
+            max_query_len: DEFAULT_MAX_QUERY_LEN,
+            keep_warm: false,
+            apply_exec_env: true,
+            highlight_color: DEFAULT_HIGHLIGHT_COLOR.to_string(),
+            highlight_text_color: None,
+            max_icon_loads: DEFAULT_MAX_ICON_LOADS,
+            animation_duration_ms: 150,
+        }
     }
 }
 
+/// Which name of an application is shown as its label, with the other one beside it.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PrimaryLabel {
+    /// Like "Firefox"
+    #[default]
+    Name,
+    /// Like "Web Browser", for applications that have one
+    GenericName,
+}
+
 impl StorableState for LauncherSettings {
     const NAME: &'static str = "settings";
 }
+
+/// The settings as they were kept in bincode state, before they moved to a config file. Only read
+/// to migrate them, so this must keep the layout they were written with.
+#[derive(Debug, bincode::Decode)]
+pub struct LegacyLauncherSettings {
+    pub transparency: f32,
+}
+
+impl StorableState for LegacyLauncherSettings {
+    const NAME: &'static str = "settings";
+}
+
+impl From<LegacyLauncherSettings> for LauncherSettings {
+    fn from(LegacyLauncherSettings { transparency }: LegacyLauncherSettings) -> Self {
+        Self {
+            transparency,
+            ..Self::default()
+        }
+    }
+}
+
+/// Parse a `#rrggbb` or `#rrggbbaa` color.
+pub fn parse_color(color: &str) -> Option<slint::Color> {
+    let hex = color.strip_prefix('#')?;
+    if !matches!(hex.len(), 6 | 8) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let channel = |idx: usize| u8::from_str_radix(&hex[idx..idx + 2], 16).ok();
+    let alpha = if hex.len() == 8 { channel(6)? } else { 255 };
+
+    Some(slint::Color::from_argb_u8(
+        alpha,
+        channel(0)?,
+        channel(2)?,
+        channel(4)?,
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn legacy_settings_are_migrated() {
+        // the state file an older polymodo wrote, with a transparency of 0.5
+        let state = [0x00, 0x00, 0x00, 0x3f];
+
+        let (legacy, _): (LegacyLauncherSettings, _) =
+            bincode::decode_from_slice(&state, bincode::config::standard()).unwrap();
+        let settings = LauncherSettings::from(legacy);
+
+        assert_eq!(settings.transparency, 0.5);
+        assert_eq!(settings.max_query_len, DEFAULT_MAX_QUERY_LEN);
+    }
+}
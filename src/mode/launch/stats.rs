@@ -0,0 +1,232 @@
+use super::history::LaunchHistory;
+use super::launcher::{format_age, Launcher};
+use crate::app::{App, AppExt, AppName, AppOptions, AppSender, JsonAppResult, Screenshot};
+use crate::mode::{window_title, HideOnDrop, HideOnDropExt};
+use crate::ui;
+use crate::ui::index_model::IndexModel;
+use slint::{ComponentHandle, ModelExt, ModelRc};
+use std::cell::Cell;
+use std::cmp::Ordering;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::SystemTime;
+
+type StatsModel = Rc<IndexModel<usize, LaunchStat>>;
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    /// Sort by this column, or reverse the order if already sorted by it.
+    SortBy(ui::StatsColumn),
+    /// Drop the launch statistics of the application in this row.
+    Forget(usize),
+}
+
+/// The launcher's launch history as a table, to see (and correct) why applications rank the way
+/// they do.
+pub struct LaunchStats {
+    stats: StatsModel,
+    window: HideOnDrop<ui::StatsWindow>,
+    history: LaunchHistory,
+    order: Rc<Cell<StatsOrder>>,
+}
+
+impl App for LaunchStats {
+    type Message = Message;
+    type Output = JsonAppResult<()>;
+
+    const NAME: AppName = AppName::LaunchStats;
+
+    fn create(message_sender: AppSender<Self::Message>, _options: &AppOptions) -> Self {
+        let window: HideOnDrop<ui::StatsWindow> = ui::StatsWindow::new().unwrap().hide_on_drop();
+        window.set_window_title(window_title(Self::NAME, None));
+
+        let model: StatsModel = Default::default();
+        let order: Rc<Cell<StatsOrder>> = Default::default();
+
+        {
+            let order = order.clone();
+            window.set_stats(ModelRc::new(
+                model
+                    .clone()
+                    .sort_by(move |a, b| order.get().compare(a, b))
+                    .map(|stat| stat.to_slint()),
+            ));
+        }
+
+        {
+            let message_sender = message_sender.clone();
+            window.on_escape_pressed(move || {
+                message_sender.finish();
+            });
+        }
+
+        {
+            let message_sender = message_sender.clone();
+            window.on_sort_by(move |column| {
+                message_sender.send_or_queue(Message::SortBy(column));
+            });
+        }
+
+        {
+            let message_sender = message_sender.clone();
+            window.on_forget(move |id| {
+                if id < 0 {
+                    return;
+                }
+
+                message_sender.send_or_queue(Message::Forget(id as usize));
+            });
+        }
+
+        // the launcher keeps the history; it's the one that launches things.
+        let history = Launcher::read_state::<LaunchHistory>()
+            .ok()
+            .unwrap_or_default();
+
+        for (id, path) in history.entries().enumerate() {
+            model.insert(
+                id,
+                LaunchStat {
+                    id,
+                    name: application_name(path),
+                    path: path.to_path_buf(),
+                    score: history.score(path),
+                    last_launched: history.last_launched(path),
+                },
+            );
+        }
+
+        let stats = LaunchStats {
+            stats: model,
+            window,
+            history,
+            order,
+        };
+        stats.apply_order();
+
+        stats.window.show().unwrap();
+
+        stats
+    }
+
+    fn on_message(&mut self, message: Self::Message) {
+        match message {
+            Message::SortBy(column) => {
+                let StatsOrder {
+                    column: sorted,
+                    descending,
+                } = self.order.get();
+                let descending = if sorted == column {
+                    !descending
+                } else {
+                    // the best, latest, or alphabetically first on top
+                    column != ui::StatsColumn::Name
+                };
+                self.order.set(StatsOrder { column, descending });
+
+                self.apply_order();
+            }
+            Message::Forget(id) => {
+                let Some(stat) = self.stats.remove(&id) else {
+                    return;
+                };
+
+                self.history.forget(&stat.path);
+                if let Err(e) = Launcher::write_state(&self.history) {
+                    log::error!("couldn't write launcher bias (scoring): {e}");
+                }
+            }
+        }
+    }
+
+    fn screenshot(&self) -> Option<Screenshot> {
+        self.window.window().take_snapshot().ok()
+    }
+
+    fn stop(self) -> Self::Output {
+        JsonAppResult(())
+    }
+}
+
+impl LaunchStats {
+    /// Sort the rows again, and show how they are sorted.
+    fn apply_order(&self) {
+        let StatsOrder { column, descending } = self.order.get();
+        self.window.set_sorted_by(column);
+        self.window.set_descending(descending);
+
+        self.stats.mutate_all(|_, _, _| {});
+    }
+}
+
+/// Which column the rows are sorted by.
+#[derive(Debug, Copy, Clone)]
+struct StatsOrder {
+    column: ui::StatsColumn,
+    descending: bool,
+}
+
+impl Default for StatsOrder {
+    fn default() -> Self {
+        Self {
+            column: ui::StatsColumn::Score,
+            descending: true,
+        }
+    }
+}
+
+impl StatsOrder {
+    fn compare(self, a: &LaunchStat, b: &LaunchStat) -> Ordering {
+        let ordering = match self.column {
+            ui::StatsColumn::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+            ui::StatsColumn::Score => a.score.total_cmp(&b.score),
+            ui::StatsColumn::LastLaunched => a.last_launched.cmp(&b.last_launched),
+        };
+
+        if self.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct LaunchStat {
+    id: usize,
+    name: String,
+    /// The desktop file that was launched
+    path: PathBuf,
+    /// How the launcher ranks this application, see [LaunchHistory::score]
+    score: f32,
+    last_launched: Option<SystemTime>,
+}
+
+impl LaunchStat {
+    fn to_slint(&self) -> ui::LaunchStatEntry {
+        ui::LaunchStatEntry {
+            name: self.name.as_str().into(),
+            path: self.path.to_string_lossy().as_ref().into(),
+            score: format!("{:.1}", self.score).into(),
+            last_launch_age: self
+                .last_launched
+                .and_then(|time| time.elapsed().ok())
+                .map(format_age)
+                .unwrap_or_else(|| "never".to_string())
+                .into(),
+            id: self.id as i32,
+        }
+    }
+}
+
+/// The name of the application the desktop file at `path` describes, or its file name if it
+/// can't be read (e.g. because the application was uninstalled).
+fn application_name(path: &Path) -> String {
+    match crate::xdg::load(path) {
+        Ok(entry) => entry.name,
+        Err(_) => path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.to_string_lossy().to_string()),
+    }
+}
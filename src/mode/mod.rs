@@ -1,7 +1,55 @@
-use slint::ComponentHandle;
+use crate::app::{AppKey, AppName, AppOptions};
+use crate::polymodo::PolymodoHandle;
+use slint::{ComponentHandle, SharedString};
 use std::ops::Deref;
+use std::process::Command;
 
+pub mod bookmarks;
 pub mod launch;
+pub mod note;
+pub mod query_history;
+
+/// Spawn the app corresponding to `app_name`.
+pub fn spawn_app(
+    polymodo: &PolymodoHandle,
+    app_name: AppName,
+    options: &AppOptions,
+) -> anyhow::Result<AppKey> {
+    match app_name {
+        AppName::Launcher => polymodo.spawn_app::<launch::Launcher>(options),
+        AppName::Note => polymodo.spawn_app::<note::Note>(options),
+        AppName::Bookmarks => polymodo.spawn_app::<bookmarks::Bookmarks>(options),
+        AppName::LaunchStats => polymodo.spawn_app::<launch::LaunchStats>(options),
+        #[cfg(test)]
+        AppName::Fake => polymodo.spawn_app::<crate::testing::FakeApp>(options),
+    }
+}
+
+/// The title of an app's window, which compositors can match on and screen readers announce.
+///
+/// Apps with distinct states (like a confirmation prompt) should pass that state along, e.g.
+/// `polymodo — confirm: Overwrite file?`.
+pub fn window_title(app_name: AppName, state: Option<&str>) -> SharedString {
+    let app_name = app_name.to_string().to_lowercase();
+
+    match state {
+        Some(state) => format!("polymodo — {app_name}: {state}").into(),
+        None => format!("polymodo — {app_name}").into(),
+    }
+}
+
+/// Spawn `command` without waiting for it to exit, and return its pid.
+///
+/// It is reaped once it exits, so that it doesn't linger as a zombie for as long as the daemon
+/// lives.
+pub fn spawn_detached(command: &mut Command) -> std::io::Result<u32> {
+    let mut child = command.spawn()?;
+    let pid = child.id();
+
+    std::thread::spawn(move || child.wait());
+
+    Ok(pid)
+}
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct HideOnDrop<T: ComponentHandle>(pub T);
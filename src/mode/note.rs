@@ -0,0 +1,329 @@
+use crate::app::{App, AppName, AppOptions, AppSender, JsonAppResult, Screenshot};
+use crate::fuzzy_search::FuzzySearch;
+use crate::mode::{window_title, HideOnDrop, HideOnDropExt};
+use crate::ui;
+use crate::ui::index_model::IndexModel;
+use indexmap::IndexSet;
+use slint::{ComponentHandle, ModelExt, ModelRc};
+use std::path::PathBuf;
+use std::rc::Rc;
+
+/// Notes are stored as CommonMark bullet list items, one per line.
+const NOTE_PREFIX: &str = "- ";
+/// Done notes are struck through. Strictly speaking this is an extension to CommonMark, but one
+/// that just about every markdown renderer understands.
+const DONE_MARKER: &str = "~~";
+
+type NotesModel = Rc<IndexModel<usize, NoteLine>>;
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    QuerySet(String),
+    /// Write a new note, and close.
+    Add(String),
+    /// Mark the note on this line as done, or as not done if it already was.
+    ToggleDone(usize),
+    SearchUpdated,
+}
+
+/// Quick-capture of notes into a markdown file.
+pub struct Note {
+    notes: NotesModel,
+    /// All lines of the notes file, including those that aren't notes
+    lines: Vec<String>,
+    window: HideOnDrop<ui::NoteWindow>,
+    sender: AppSender<Message>,
+    search: FuzzySearch<1, SearchEntry>,
+    searching: bool,
+    /// The note that was added, if any
+    added: Option<String>,
+}
+
+impl App for Note {
+    type Message = Message;
+    type Output = JsonAppResult<Option<String>>;
+
+    const NAME: AppName = AppName::Note;
+
+    fn create(message_sender: AppSender<Self::Message>, _options: &AppOptions) -> Self {
+        let lines: Vec<String> = notes_file()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .map(|content| content.lines().map(str::to_string).collect())
+            .unwrap_or_default();
+
+        let window: HideOnDrop<ui::NoteWindow> = ui::NoteWindow::new().unwrap().hide_on_drop();
+        window.set_window_title(window_title(Self::NAME, None));
+
+        let model: NotesModel = Default::default();
+
+        // best matches first; most recent notes first otherwise.
+        window.set_notes(ModelRc::new(
+            model
+                .clone()
+                .filter(|note| note.shown)
+                .sort_by(|a, b| (b.score, b.line).cmp(&(a.score, a.line)))
+                .map(|note| note.to_slint()),
+        ));
+
+        let search: FuzzySearch<1, SearchEntry> =
+            FuzzySearch::create_with_config(nucleo::Config::DEFAULT);
+
+        for (line, text) in lines.iter().enumerate() {
+            let Some((text, done)) = parse_note(text) else {
+                continue;
+            };
+
+            search.push(SearchEntry {
+                line,
+                text: text.to_string(),
+            });
+            model.insert(
+                line,
+                NoteLine {
+                    line,
+                    shown: true,
+                    score: 0,
+                    text: text.to_string(),
+                    done,
+                },
+            );
+        }
+
+        {
+            let notify = search.notify();
+            let sender = message_sender.clone();
+            message_sender.spawn(async move {
+                loop {
+                    notify.acquire().await;
+
+                    sender.send_async(Message::SearchUpdated).await;
+                }
+            });
+        }
+
+        {
+            let message_sender = message_sender.clone();
+            window.on_query_edited(move |query| {
+                message_sender.send_or_queue(Message::QuerySet(query.as_str().to_string()));
+            });
+        }
+
+        {
+            let message_sender = message_sender.clone();
+            window.on_escape_pressed(move || {
+                message_sender.finish();
+            });
+        }
+
+        {
+            let message_sender = message_sender.clone();
+            window.on_add_note(move |text| {
+                message_sender.send_or_queue(Message::Add(text.as_str().to_string()));
+            });
+        }
+
+        {
+            let message_sender = message_sender.clone();
+            window.on_toggle_done(move |id| {
+                if id < 0 {
+                    return;
+                }
+
+                message_sender.send_or_queue(Message::ToggleDone(id as usize));
+            });
+        }
+
+        window.show().unwrap();
+
+        Note {
+            notes: model,
+            lines,
+            window,
+            sender: message_sender,
+            search,
+            searching: false,
+            added: None,
+        }
+    }
+
+    fn on_message(&mut self, message: Self::Message) {
+        match message {
+            Message::QuerySet(query) => {
+                self.searching = !query.is_empty();
+                self.search.search::<0>(query);
+            }
+            Message::Add(text) => {
+                let text = text.trim();
+                if text.is_empty() {
+                    return;
+                }
+
+                self.lines
+                    .push(format_note(&format!("{} {text}", timestamp()), false));
+                self.save();
+
+                self.added = Some(text.to_string());
+                self.sender.finish();
+            }
+            Message::ToggleDone(line) => {
+                let Some(formatted) = self.notes.mutate_by_key(&line, |_, _, note| {
+                    note.done = !note.done;
+                    format_note(&note.text, note.done)
+                }) else {
+                    return;
+                };
+
+                self.lines[line] = formatted;
+                self.save();
+            }
+            Message::SearchUpdated => {
+                self.search.tick();
+
+                let ranked: IndexSet<usize> = self
+                    .search
+                    .get_matches()
+                    .into_iter()
+                    .map(|entry| entry.line)
+                    .collect();
+                let searching = self.searching;
+
+                // the best match is now on top
+                self.window.set_current_note(0);
+
+                self.notes.mutate_all(|_, line, note| {
+                    let position = ranked.get_index_of(line).map(|pos| ranked.len() - pos);
+                    note.shown = position.is_some();
+                    // without a query, every note matches equally well; let the order of the
+                    // file decide instead.
+                    note.score = if searching {
+                        position.unwrap_or_default()
+                    } else {
+                        0
+                    };
+                });
+            }
+        }
+    }
+
+    fn screenshot(&self) -> Option<Screenshot> {
+        self.window.window().take_snapshot().ok()
+    }
+
+    fn stop(self) -> Self::Output {
+        JsonAppResult(self.added)
+    }
+}
+
+impl Note {
+    /// Write all lines back to the notes file.
+    fn save(&self) {
+        let Some(path) = notes_file() else {
+            log::error!("couldn't determine where to store notes");
+            return;
+        };
+
+        let mut content = self.lines.join("\n");
+        content.push('\n');
+
+        if let Err(e) = std::fs::write(&path, content) {
+            log::error!("couldn't write notes to {}: {e}", path.display());
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct NoteLine {
+    /// The line of the notes file this note is on
+    line: usize,
+    /// Whether this note should be shown in the UI
+    shown: bool,
+    /// The score this note got from the fuzzy matcher
+    score: usize,
+    text: String,
+    done: bool,
+}
+
+impl NoteLine {
+    fn to_slint(&self) -> ui::NoteEntry {
+        ui::NoteEntry {
+            text: self.text.as_str().into(),
+            done: self.done,
+            id: self.line as i32,
+        }
+    }
+}
+
+struct SearchEntry {
+    line: usize,
+    text: String,
+}
+
+impl crate::fuzzy_search::Row<1> for SearchEntry {
+    type Output = String;
+
+    fn columns(&self) -> [Self::Output; 1] {
+        [self.text.clone()]
+    }
+}
+
+/// `$XDG_DATA_HOME/polymodo/notes.md`, making sure its parent directory exists.
+fn notes_file() -> Option<PathBuf> {
+    let data_home = crate::persistence::get_polymodo_data_home()?;
+
+    if !data_home.exists() {
+        std::fs::create_dir_all(data_home.as_path()).ok()?;
+    }
+
+    Some(data_home.join("notes.md"))
+}
+
+/// Parse a line of the notes file into the note's text and whether it is done.
+/// Returns `None` for lines that aren't notes.
+fn parse_note(line: &str) -> Option<(&str, bool)> {
+    let text = line.strip_prefix(NOTE_PREFIX)?;
+
+    match text
+        .strip_prefix(DONE_MARKER)
+        .and_then(|text| text.strip_suffix(DONE_MARKER))
+    {
+        Some(text) => Some((text, true)),
+        None => Some((text, false)),
+    }
+}
+
+fn format_note(text: &str, done: bool) -> String {
+    if done {
+        format!("{NOTE_PREFIX}{DONE_MARKER}{text}{DONE_MARKER}")
+    } else {
+        format!("{NOTE_PREFIX}{text}")
+    }
+}
+
+/// The current local time, see [format_timestamp].
+fn timestamp() -> String {
+    format_timestamp(chrono::Local::now().naive_local())
+}
+
+/// Format `time` as `YYYY-MM-DD HH:MM`.
+fn format_timestamp(time: chrono::NaiveDateTime) -> String {
+    time.format("%Y-%m-%d %H:%M").to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn timestamps_are_zero_padded() {
+        let time = NaiveDate::from_ymd_opt(2024, 3, 7)
+            .and_then(|date| date.and_hms_opt(9, 5, 59))
+            .unwrap();
+        assert_eq!(format_timestamp(time), "2024-03-07 09:05");
+
+        let time = NaiveDate::from_ymd_opt(1999, 12, 31)
+            .and_then(|date| date.and_hms_opt(23, 59, 0))
+            .unwrap();
+        assert_eq!(format_timestamp(time), "1999-12-31 23:59");
+    }
+}
@@ -0,0 +1,212 @@
+use crate::app::{App, AppExt};
+use crate::cache::unix_time;
+use crate::persistence::StorableState;
+use nucleo::pattern::{CaseMatching, Normalization, Pattern};
+use nucleo::Utf32Str;
+use std::collections::VecDeque;
+use std::time::{Duration, SystemTime};
+
+/// How many queries are remembered per app.
+const MAX_QUERIES: usize = 100;
+
+/// Queries previously entered in an app, oldest first.
+#[derive(Debug, Default, Clone, bincode::Decode, bincode::Encode)]
+struct QueryHistory {
+    queries: VecDeque<String>,
+}
+
+impl StorableState for QueryHistory {
+    const NAME: &'static str = "query_history";
+}
+
+/// Shell-like recall of previous queries, persisted per app.
+///
+/// Apps that take secret input (like passwords) must never record it here.
+#[derive(Debug, Default)]
+pub struct QueryRecall {
+    history: QueryHistory,
+    /// The query in `history` that is currently recalled, if any
+    cursor: Option<usize>,
+    /// What the recalled query was searched for with, see [QueryRecall::search]
+    pattern: Option<String>,
+}
+
+impl QueryRecall {
+    pub fn load<A: App>() -> Self {
+        Self {
+            history: A::read_state().unwrap_or_default(),
+            cursor: None,
+            pattern: None,
+        }
+    }
+
+    /// Recall the previous (if `older`) or next query. Stepping past the most recent query
+    /// stops recalling, and returns an empty query.
+    pub fn step(&mut self, older: bool) -> String {
+        let len = self.history.queries.len();
+        self.pattern = None;
+
+        self.cursor = match (self.cursor, older) {
+            (None, true) => len.checked_sub(1),
+            (None, false) => None,
+            (Some(idx), true) => Some(idx.saturating_sub(1)),
+            (Some(idx), false) => (idx + 1 < len).then_some(idx + 1),
+        };
+
+        self.cursor
+            .and_then(|idx| self.history.queries.get(idx))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Like a shell's reverse search: recall the most recent query that fuzzily matches
+    /// `pattern`. Searching again while a found query is recalled finds the next older one
+    /// matching the first pattern, rather than the recalled query itself.
+    ///
+    /// Returns `None` if no (older) query matches, in which case the recalled query stays.
+    pub fn search(&mut self, pattern: &str) -> Option<String> {
+        let (pattern, before) = match (&self.pattern, self.cursor) {
+            (Some(pattern), Some(idx)) => (pattern.clone(), idx),
+            _ => (pattern.to_string(), self.history.queries.len()),
+        };
+
+        let mut matcher = nucleo::Matcher::new(nucleo::Config::DEFAULT);
+        let parsed = Pattern::parse(&pattern, CaseMatching::Ignore, Normalization::Never);
+        let mut buf = Vec::new();
+        let idx = (0..before).rev().find(|&idx| {
+            let query = Utf32Str::new(&self.history.queries[idx], &mut buf);
+            parsed.score(query, &mut matcher).is_some()
+        })?;
+
+        self.cursor = Some(idx);
+        self.pattern = Some(pattern);
+
+        Some(self.history.queries[idx].clone())
+    }
+
+    pub fn is_recalling(&self) -> bool {
+        self.cursor.is_some()
+    }
+
+    /// The user edited the query: stop recalling.
+    pub fn reset(&mut self) {
+        self.cursor = None;
+        self.pattern = None;
+    }
+
+    /// Remember `query` as the most recent one, and persist the history.
+    pub fn record<A: App>(&mut self, query: &str) {
+        self.reset();
+
+        let query = query.trim();
+        if query.is_empty() {
+            return;
+        }
+
+        let queries = &mut self.history.queries;
+        queries.retain(|q| q != query);
+        queries.push_back(query.to_string());
+
+        while queries.len() > MAX_QUERIES {
+            queries.pop_front();
+        }
+
+        if let Err(e) = A::write_state(&self.history) {
+            log::error!("couldn't write query history: {e}");
+        }
+    }
+}
+
+/// The query an app was closed with, so that reopening it soon after continues where the user
+/// left off.
+#[derive(Debug, Default, Clone, bincode::Decode, bincode::Encode)]
+pub struct LastQuery {
+    pub query: String,
+    /// What was selected, in whatever way the app identifies its entries
+    pub selection: Option<String>,
+    /// When the app was closed, in seconds since the unix epoch
+    closed_at: u64,
+}
+
+impl StorableState for LastQuery {
+    const NAME: &'static str = "last_query";
+}
+
+impl LastQuery {
+    /// Remember `query` and `selection` as what the app is being closed with.
+    pub fn save<A: App>(query: &str, selection: Option<String>) {
+        let last = LastQuery {
+            query: query.to_string(),
+            selection,
+            closed_at: unix_time(SystemTime::now()).unwrap_or_default(),
+        };
+
+        if let Err(e) = A::write_state(&last) {
+            log::error!("couldn't write last query: {e}");
+        }
+    }
+
+    /// The query the app was last closed with, if that was at most `within` ago and there was
+    /// a query at all. A zero `within` never restores anything.
+    pub fn restore<A: App>(within: Duration) -> Option<Self> {
+        if within.is_zero() {
+            return None;
+        }
+
+        let last = A::read_state::<LastQuery>().ok()?;
+        let now = unix_time(SystemTime::now())?;
+
+        let recent = now.saturating_sub(last.closed_at) <= within.as_secs();
+        (recent && !last.query.is_empty()).then_some(last)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn recall(queries: &[&str]) -> QueryRecall {
+        QueryRecall {
+            history: QueryHistory {
+                queries: queries.iter().map(|query| query.to_string()).collect(),
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn queries_are_recalled_newest_first() {
+        let mut recall = recall(&["firefox", "gimp", "steam"]);
+
+        assert_eq!(recall.step(true), "steam");
+        assert_eq!(recall.step(true), "gimp");
+        assert_eq!(recall.step(true), "firefox");
+        // the oldest query stays
+        assert_eq!(recall.step(true), "firefox");
+        assert_eq!(recall.step(false), "gimp");
+        assert_eq!(recall.step(false), "steam");
+        // past the newest query: back to typing a new one
+        assert_eq!(recall.step(false), "");
+        assert!(!recall.is_recalling());
+    }
+
+    #[test]
+    fn history_is_searched_newest_first() {
+        let mut recall = recall(&["firefox", "gimp", "files", "steam"]);
+
+        assert_eq!(recall.search("fi").as_deref(), Some("files"));
+        // searching again continues with older matches of the same pattern
+        assert_eq!(recall.search("files").as_deref(), Some("firefox"));
+        assert_eq!(recall.search("firefox"), None);
+        assert!(recall.is_recalling());
+
+        // a new search starts over from the newest query
+        recall.reset();
+        assert_eq!(recall.search("GMP").as_deref(), Some("gimp"));
+        assert_eq!(recall.search("gimp"), None);
+
+        recall.reset();
+        assert_eq!(recall.search("blender"), None);
+        assert!(!recall.is_recalling());
+    }
+}
@@ -35,6 +35,12 @@ impl Notify {
         semaphore_guard.forget(); // Prevents the permit from returning
     }
 
+    /// Whether anyone besides this handle could be waiting for notifications: handles that
+    /// are the last of their kind notify no one.
+    pub fn has_listeners(&self) -> bool {
+        Arc::strong_count(&self.inner) > 1
+    }
+
     #[expect(unused)]
     pub fn acquire_blocking(&self) -> SemaphoreGuard<'_> {
         self.inner.acquire_blocking()
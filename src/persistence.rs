@@ -1,17 +1,56 @@
-use std::path::PathBuf;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
 const BINCODE_CONFIG: bincode::config::Configuration = bincode::config::standard();
 
+/// The directory given with `--config`, if any. See [set_config_dir].
+static CONFIG_DIR: OnceLock<PathBuf> = OnceLock::new();
+
 pub trait StorableState {
     const NAME: &'static str;
 }
 
+/// Keep configuration in `dir`, and state in `dir/state`, rather than in `$XDG_CONFIG_HOME` and
+/// `$XDG_STATE_HOME`. This must be called before anything is read, and only once.
+pub fn set_config_dir(dir: PathBuf) {
+    if CONFIG_DIR.set(dir).is_err() {
+        log::warn!("the config directory was already set");
+    }
+}
+
+/// The directory given with `--config`, if any.
+pub fn config_dir() -> Option<&'static Path> {
+    CONFIG_DIR.get().map(PathBuf::as_path)
+}
+
 pub fn get_polymodo_state_home() -> Option<PathBuf> {
+    if let Some(dir) = config_dir() {
+        return Some(dir.join("state"));
+    }
+
     let xdg = xdg::BaseDirectories::new();
 
     xdg.state_home.map(|st| st.join("polymodo"))
 }
 
+pub fn get_polymodo_data_home() -> Option<PathBuf> {
+    let xdg = xdg::BaseDirectories::new();
+
+    xdg.data_home.map(|data| data.join("polymodo"))
+}
+
+pub fn get_polymodo_config_home() -> Option<PathBuf> {
+    if let Some(dir) = config_dir() {
+        return Some(dir.to_path_buf());
+    }
+
+    let xdg = xdg::BaseDirectories::new();
+
+    xdg.config_home.map(|config| config.join("polymodo"))
+}
+
 fn state_file(app_name: &str, state_name: &str) -> Option<PathBuf> {
     let app_home = get_polymodo_state_home().map(|path| path.join(app_name))?;
 
@@ -53,3 +92,137 @@ pub fn write_state<S: bincode::Encode>(
     bincode::encode_into_std_write(state, &mut buf_write, BINCODE_CONFIG)
         .map_err(std::io::Error::other)
 }
+
+/// `$XDG_CONFIG_HOME/polymodo/<app_name>/<config_name>.json`, where `app_name` is like
+/// [crate::app::AppName::config_dir_name].
+fn config_file(app_name: &str, config_name: &str) -> Option<PathBuf> {
+    let app_home = get_polymodo_config_home().map(|path| path.join(app_name))?;
+
+    // Ensure that the parent of the config file exists, recursively.
+    if !app_home.exists() {
+        std::fs::create_dir_all(app_home.as_path()).ok()?;
+    }
+
+    Some(app_home.join(config_name).with_extension("json"))
+}
+
+/// Read configuration that users may edit by hand, unlike [read_state].
+///
+/// A config file that doesn't exist is an error, but one that can't be parsed isn't: the problem
+/// is logged, and the defaults are used instead. The file is moved aside to `<name>.json.bak`,
+/// so that writing the defaults back doesn't destroy the user's edits.
+pub fn read_config<S: DeserializeOwned + Default>(
+    app_name: &str,
+    config_name: &str,
+) -> std::io::Result<S> {
+    let file = config_file(app_name, config_name)
+        .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::NotFound))?;
+
+    read_config_file(&file)
+}
+
+fn read_config_file<S: DeserializeOwned + Default>(file: &Path) -> std::io::Result<S> {
+    let config = std::fs::read_to_string(file)?;
+
+    if let Some(config) = parse_config(file, &config) {
+        return Ok(config);
+    }
+
+    let backup = file.with_extension("json.bak");
+    match std::fs::rename(file, &backup) {
+        Ok(()) => log::warn!("moved {} aside to {}", file.display(), backup.display()),
+        Err(e) => log::error!("couldn't move {} aside: {e}", file.display()),
+    }
+
+    Ok(S::default())
+}
+
+/// Parse the `config` read from `file`, logging where it is invalid if it is.
+fn parse_config<S: DeserializeOwned>(file: &Path, config: &str) -> Option<S> {
+    serde_json::from_str(config)
+        .inspect_err(|e| {
+            log::error!(
+                "{}:{}:{}: {e}; using the defaults instead",
+                file.display(),
+                e.line(),
+                e.column()
+            )
+        })
+        .ok()
+}
+
+pub fn write_config<S: Serialize>(
+    app_name: &str,
+    config_name: &str,
+    config: S,
+) -> std::io::Result<()> {
+    let file = config_file(app_name, config_name)
+        .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::NotFound))?;
+
+    let file = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(file)?;
+    let mut buf_write = std::io::BufWriter::new(file);
+
+    serde_json::to_writer_pretty(&mut buf_write, &config)?;
+    std::io::Write::write_all(&mut buf_write, b"\n")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    #[serde(default)]
+    struct Config {
+        name: String,
+        size: u32,
+    }
+
+    impl Default for Config {
+        fn default() -> Self {
+            Self {
+                name: "default".to_string(),
+                size: 12,
+            }
+        }
+    }
+
+    #[test]
+    fn missing_settings_keep_their_default() {
+        let file = Path::new("config.json");
+
+        let config: Option<Config> = parse_config(file, r#"{ "size": 14 }"#);
+        assert_eq!(
+            config,
+            Some(Config {
+                size: 14,
+                ..Config::default()
+            })
+        );
+
+        let config: Option<Config> = parse_config(file, r#"{ "size": "large" }"#);
+        assert_eq!(config, None);
+    }
+
+    #[test]
+    fn invalid_config_is_moved_aside() {
+        let dir = std::env::temp_dir().join(format!("polymodo-config-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("settings.json");
+        std::fs::write(&file, r#"{ "size": 14, }"#).unwrap();
+
+        let config: Config = read_config_file(&file).unwrap();
+
+        assert_eq!(config, Config::default());
+        assert!(!file.exists());
+        assert_eq!(
+            std::fs::read_to_string(dir.join("settings.json.bak")).unwrap(),
+            r#"{ "size": 14, }"#
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
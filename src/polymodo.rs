@@ -1,37 +1,155 @@
 use crate::app;
-use crate::app::{AppEvent, AppMessage, AppResult, AppSender};
-use slint::JoinHandle;
-use std::cell::RefCell;
+use crate::app::{Abortable, AppEvent, AppMessage, AppResult, AppSender};
+use crate::compositor::Toplevel;
+use crate::notify::Notify;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
+use std::future::Future;
 use std::ops::Deref;
+use std::pin::Pin;
 use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
 
 type FinishSender = oneshot::Sender<Option<Box<dyn AppResult + Send>>>;
 
+pub type LocalFuture = Pin<Box<dyn Future<Output = ()>>>;
+
+/// How many events apps may have queued up for polymodo, before their messages are discarded
+/// (see [AppSender::send]). Only an app sending messages in a loop should ever get there.
+const APP_EVENT_CAPACITY: usize = 1024;
+
+/// The event loop polymodo and its apps run their tasks on.
+///
+/// This is slint's event loop, except in tests, which have no windowing system to talk to.
+#[derive(Debug, Copy, Clone)]
+pub struct EventLoop {
+    /// Run a future on the event loop's thread. Dropping the returned handle does not cancel it.
+    pub spawn_local: fn(LocalFuture) -> Box<dyn Abortable + Send>,
+    pub quit: fn(),
+}
+
+impl EventLoop {
+    pub const SLINT: EventLoop = EventLoop {
+        spawn_local: |future| Box::new(slint::spawn_local(future).expect("an event loop")),
+        quit: || {
+            if let Err(e) = slint::quit_event_loop() {
+                log::error!("failed to quit the event loop: {e}");
+            }
+        },
+    };
+
+    pub fn spawn(&self, future: impl Future<Output = ()> + 'static) -> Box<dyn Abortable + Send> {
+        (self.spawn_local)(Box::pin(future))
+    }
+}
+
 pub struct Polymodo {
     apps: RefCell<HashMap<app::AppKey, Box<dyn app::AppDriver>>>,
+    // apps that hid themselves rather than stopping, to be reopened; see [app::App::hide]
+    hidden_apps: RefCell<HashMap<app::AppKey, Box<dyn app::AppDriver>>>,
     app_finish_senders: RefCell<HashMap<app::AppKey, FinishSender>>,
     app_message_channel: (
         smol::channel::Sender<AppEvent>,
         smol::channel::Receiver<AppEvent>,
     ),
+    // unbounded, for the events apps send besides their messages: see [AppSender::send]
+    app_control_channel: (
+        smol::channel::Sender<AppEvent>,
+        smol::channel::Receiver<AppEvent>,
+    ),
+    // notified whenever an app is removed from `apps`
+    apps_changed: Notify,
+    event_loop: EventLoop,
+    started_at: Instant,
+    // when an app last stopped, or a client last sent a message, see [Polymodo::idle_for]
+    last_activity: Cell<Instant>,
+    // how long apps may take to stop, see [Polymodo::stop_app]
+    app_stop_timeout: Duration,
+    // how many apps may be in `hidden_apps`, at most one of each kind
+    max_hidden_apps: usize,
+    // whether to give focus back to the window that had it before an app opened, see
+    // [crate::compositor]
+    return_focus: bool,
+    // the window that had focus before each app opened, if `return_focus` is set
+    previous_focus: RefCell<HashMap<app::AppKey, Toplevel>>,
 }
 
 impl Polymodo {
     pub fn new() -> Self {
-        let channel = smol::channel::unbounded::<AppEvent>();
+        Self::with_event_loop(EventLoop::SLINT)
+    }
+
+    pub fn with_event_loop(event_loop: EventLoop) -> Self {
+        let channel = smol::channel::bounded::<AppEvent>(APP_EVENT_CAPACITY);
 
         Self {
             apps: Default::default(),
+            hidden_apps: Default::default(),
             app_finish_senders: Default::default(),
             app_message_channel: channel,
+            app_control_channel: smol::channel::unbounded(),
+            apps_changed: Notify::new(),
+            event_loop,
+            started_at: Instant::now(),
+            last_activity: Cell::new(Instant::now()),
+            app_stop_timeout: crate::config::Options::default().app_stop_timeout,
+            max_hidden_apps: crate::config::Options::default().max_hidden_apps,
+            return_focus: crate::config::Options::default().return_focus,
+            previous_focus: Default::default(),
         }
     }
 
+    pub fn with_max_hidden_apps(self, max_hidden_apps: usize) -> Self {
+        Self {
+            max_hidden_apps,
+            ..self
+        }
+    }
+
+    pub fn with_return_focus(self, return_focus: bool) -> Self {
+        Self {
+            return_focus,
+            ..self
+        }
+    }
+
+    pub fn with_app_stop_timeout(self, app_stop_timeout: Duration) -> Self {
+        Self {
+            app_stop_timeout,
+            ..self
+        }
+    }
+
+    pub fn event_loop(&self) -> EventLoop {
+        self.event_loop
+    }
+
+    /// How long ago this instance was created, i.e. for how long the daemon has been running.
+    pub fn uptime(&self) -> Duration {
+        self.started_at.elapsed()
+    }
+
+    /// Note that the daemon is being used right now, see [Polymodo::idle_for].
+    pub fn mark_active(&self) {
+        self.last_activity.set(Instant::now());
+    }
+
+    /// For how long the daemon hasn't been used: no app has been open, and no client has sent a
+    /// message. `None` if an app is open right now; hidden apps don't count.
+    pub fn idle_for(&self) -> Option<Duration> {
+        self.apps
+            .borrow()
+            .is_empty()
+            .then(|| self.last_activity.get().elapsed())
+    }
+
     pub async fn wait_for_app_stop(
         &self,
         app_key: app::AppKey,
-    ) -> anyhow::Result<Option<Box<dyn AppResult + Send>>> {
+    ) -> Result<Option<Box<dyn AppResult + Send>>, PolymodoError> {
         // set up the channel of a "finish sender" stored in Polymodo:
         let (sender, receiver) = oneshot::channel();
 
@@ -52,79 +170,134 @@ impl Polymodo {
         Ok(receiver.await?)
     }
 
-    /// Stop an app. Returns its output value, boxed as any.
-    async fn stop_app(&self, app: app::AppKey) -> Result<Box<dyn AppResult + Send>, PolymodoError> {
+    /// Stop an app, or hide it if it would rather be reopened later (see [app::App::hide]).
+    /// Returns its output value, boxed as any.
+    ///
+    /// Only as many apps as configured are kept hidden, and only one of each kind: the others are
+    /// stopped after all.
+    ///
+    /// Apps stop on the event loop, so one that gets stuck stopping hangs the daemon until it
+    /// does stop. If stopping takes longer than the configured timeout, that is logged as an
+    /// error, and an app that was slow to hide is dropped rather than kept.
+    ///
+    /// As the result is returned here, anyone waiting on [Polymodo::wait_for_app_stop] for this app
+    /// is told that its result is unavailable.
+    pub async fn stop_app(
+        &self,
+        app_key: app::AppKey,
+    ) -> Result<Box<dyn AppResult + Send>, PolymodoError> {
         let mut app = self
             .apps
             .borrow_mut()
-            .remove(&app)
-            .ok_or(PolymodoError::NoSuchApp(app))?;
+            .remove(&app_key)
+            .ok_or(PolymodoError::NoSuchApp(app_key))?;
+
+        let started = Instant::now();
+        let watchdog = StopWatchdog::arm(app.app_name(), self.app_stop_timeout);
+        let result = match self.may_hide(app.app_name()).then(|| app.hide()).flatten() {
+            Some(result) if started.elapsed() < self.app_stop_timeout => {
+                self.hidden_apps.borrow_mut().insert(app_key, app);
+                result
+            }
+            Some(result) => {
+                log::warn!("{} was stuck hiding, dropping it", app.app_name());
+                result
+            }
+            None => app.stop(),
+        };
+        drop(watchdog);
+        self.apps_changed.notify();
+        self.mark_active();
+
+        // some compositors don't give focus back to where it was once our window closes
+        let previous_focus = self.previous_focus.borrow_mut().remove(&app_key);
+        if let Some(toplevel) = previous_focus {
+            // the compositor may be slow to answer, which mustn't hold up the event loop
+            smol::unblock(move || {
+                if let Err(e) = toplevel.focus() {
+                    log::debug!("couldn't return focus to {toplevel:?}: {e:#}");
+                }
+            })
+            .detach();
+        }
 
-        Ok(app.stop())
+        if let Some(sender) = self.app_finish_senders.borrow_mut().remove(&app_key) {
+            let _ = sender.send(None);
+        }
+
+        Ok(result)
     }
 
-    /// Receive one message from the messages channel (potentially waiting if there are none) and
-    /// forward it to the app it came from.
-    async fn handle_app_message(&self) {
-        let Ok(AppEvent { app_key, message }) = self.app_message_channel.1.recv().await else {
-            // `recv` only returns an error if the channel is closed (impossible: `app_message_channel` holds a sender),
-            // or full (impossible: we make an unbounded channel!),
-            // thus this should really never happen.
-            unreachable!();
-        };
+    /// Whether there is room for an app named `app_name` among the hidden apps.
+    fn may_hide(&self, app_name: app::AppName) -> bool {
+        let hidden_apps = self.hidden_apps.borrow();
 
-        match message {
-            AppMessage::Finished => {
-                let Ok(result) = self.stop_app(app_key).await else {
-                    log::error!("got a Finished message for an app that doesn't exist");
-                    return;
-                };
+        hidden_apps.len() < self.max_hidden_apps
+            && !hidden_apps.values().any(|app| app.app_name() == app_name)
+    }
 
-                // check if anyone's listening for this app's result:
-                let mut senders = self.app_finish_senders.borrow_mut();
-                if let Some(sender) = senders.remove(&app_key) {
-                    if sender.send(Some(result)).is_err() {
-                        log::warn!(
-                            "could not deliver app result because the receiver has been dropped"
-                        );
-                    }
-                } else {
-                    // no one's listening. do we want to log the result somehow?
-                    log::warn!("app finished, but no listener was registered for its result");
-                }
-            }
-            AppMessage::Message(message) => {
-                // handling messages requires mutable access to the app,
-                // so we lock apps here.
-                let mut apps = self.apps.borrow_mut();
-                let Some(app) = apps.get_mut(&app_key) else {
-                    // might happen if an app sends a message, but is stopped before that message ever gets processed.
-                    log::warn!("failed to send message to app, because app does not exist.");
-                    return;
-                };
+    /// Stop hidden apps that can't be reopened anymore, see [app::App::can_reopen].
+    fn stop_stale_hidden_apps(&self) {
+        let stale = self
+            .hidden_apps
+            .borrow()
+            .iter()
+            .filter(|(_, app)| !app.can_reopen())
+            .map(|(key, _)| *key)
+            .collect::<Vec<_>>();
+
+        for key in stale {
+            let Some(mut app) = self.hidden_apps.borrow_mut().remove(&key) else {
+                continue;
+            };
+            log::debug!("{} can't be reopened anymore, stopping it", app.app_name());
+
+            // its result was returned when it was hidden
+            let watchdog = StopWatchdog::arm(app.app_name(), self.app_stop_timeout);
+            drop(app.stop());
+            drop(watchdog);
+        }
+    }
 
-                app.on_message(message);
+    /// Wait until there are no more apps running.
+    pub async fn wait_for_all_apps(&self) {
+        while !self.apps.borrow().is_empty() {
+            self.apps_changed.acquire().await;
+        }
+    }
 
-                drop(apps); // explicitly release the lock, in case we ever add code below here ;)
-            }
-            AppMessage::SpawnLocal(abortable) => {
-                let mut apps = self.apps.borrow_mut();
-                let Some(app) = apps.get_mut(&app_key) else {
-                    log::warn!("cannot attach task to app, because app does not exist.");
-                    return;
-                };
+    /// Run `fun` on the app with this `app_key`, whether it is running or hidden.
+    /// Returns `None` if there is no such app.
+    fn with_app<R>(
+        &self,
+        app_key: app::AppKey,
+        fun: impl FnOnce(&mut dyn app::AppDriver) -> R,
+    ) -> Option<R> {
+        let mut apps = self.apps.borrow_mut();
+        let mut hidden_apps = self.hidden_apps.borrow_mut();
 
-                app.add_abortable(abortable);
+        let app = apps
+            .get_mut(&app_key)
+            .or_else(|| hidden_apps.get_mut(&app_key))?;
 
-                drop(apps);
-            }
-        }
+        Some(fun(app.as_mut()))
     }
 
     pub fn app_sender<M: Send + 'static>(&self, app_key: app::AppKey) -> AppSender<M> {
         let sender = self.app_message_channel.0.clone();
+        let control = self.app_control_channel.0.clone();
+
+        AppSender::new(app_key, sender, control, self.event_loop)
+    }
 
-        AppSender::new(app_key, sender)
+    /// The amount of apps that are currently running.
+    pub fn app_count(&self) -> usize {
+        self.apps.borrow().len()
+    }
+
+    #[cfg(test)]
+    pub fn hidden_app_count(&self) -> usize {
+        self.hidden_apps.borrow().len()
     }
 
     /// Is an app with this `app_name` running?
@@ -133,15 +306,152 @@ impl Polymodo {
         apps.values().any(|x| x.app_name() == app_name)
     }
 
+    /// Returns the key of a running app with this `app_name`, if any.
+    pub fn running_app_key(&self, app_name: app::AppName) -> Option<app::AppKey> {
+        let apps = self.apps.borrow();
+        apps.iter()
+            .find(|(_, app)| app.app_name() == app_name)
+            .map(|(key, _)| *key)
+    }
+
+    /// The diagnostic state of a running app with this `app_name`, if any.
+    pub fn app_state(&self, app_name: app::AppName) -> Option<serde_json::Value> {
+        let apps = self.apps.borrow();
+        apps.values()
+            .find(|app| app.app_name() == app_name)
+            .map(|app| app.serialize_state())
+    }
+
+    /// What the window of a running app with this `app_name` currently shows, if any, see
+    /// [app::App::screenshot].
+    pub fn app_screenshot(&self, app_name: app::AppName) -> Option<Option<app::Screenshot>> {
+        let apps = self.apps.borrow();
+        apps.values()
+            .find(|app| app.app_name() == app_name)
+            .map(|app| app.screenshot())
+    }
+
+    /// Ask an app to finish, as if it had called [AppSender::finish] itself.
+    /// Its result is delivered to anyone waiting on [Polymodo::wait_for_app_stop].
+    pub fn finish_app(&self, app_key: app::AppKey) {
+        self.app_sender::<()>(app_key).finish();
+    }
+
     pub fn into_handle(self) -> PolymodoHandle {
         PolymodoHandle(Rc::new(self))
     }
 }
 
+/// Logs an error if it isn't dropped within a timeout, see [Polymodo::stop_app].
+///
+/// Apps stop synchronously on the event loop, so one that is stuck can't be interrupted; but it
+/// is reported, instead of the daemon silently not responding anymore.
+struct StopWatchdog {
+    id: u64,
+}
+
+enum Watch {
+    Arm {
+        id: u64,
+        app_name: app::AppName,
+        timeout: Duration,
+    },
+    Disarm(u64),
+}
+
+impl StopWatchdog {
+    fn arm(app_name: app::AppName, timeout: Duration) -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+
+        let _ = watchdog().send(Watch::Arm {
+            id,
+            app_name,
+            timeout,
+        });
+
+        Self { id }
+    }
+}
+
+impl Drop for StopWatchdog {
+    fn drop(&mut self) {
+        let _ = watchdog().send(Watch::Disarm(self.id));
+    }
+}
+
+/// The one thread watching every armed [StopWatchdog], started when the first one is armed.
+fn watchdog() -> &'static mpsc::Sender<Watch> {
+    static WATCHDOG: OnceLock<mpsc::Sender<Watch>> = OnceLock::new();
+
+    WATCHDOG.get_or_init(|| {
+        let (sender, receiver) = mpsc::channel();
+
+        std::thread::Builder::new()
+            .name("stop watchdog".to_string())
+            .spawn(move || watch_stops(receiver))
+            .expect("failed to start the stop watchdog");
+
+        sender
+    })
+}
+
+fn watch_stops(receiver: mpsc::Receiver<Watch>) {
+    // app name, timeout and deadline of each armed watchdog
+    let mut armed: HashMap<u64, (app::AppName, Duration, Instant)> = HashMap::new();
+    // the ones that already missed their deadline
+    let mut stuck: HashMap<u64, (app::AppName, Instant)> = HashMap::new();
+
+    loop {
+        let next_deadline = armed.values().map(|(_, _, deadline)| *deadline).min();
+        let watch = match next_deadline {
+            Some(deadline) => {
+                receiver.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            }
+            None => receiver.recv().map_err(RecvTimeoutError::from),
+        };
+
+        match watch {
+            Ok(Watch::Arm {
+                id,
+                app_name,
+                timeout,
+            }) => {
+                armed.insert(id, (app_name, timeout, Instant::now() + timeout));
+            }
+            Ok(Watch::Disarm(id)) => {
+                armed.remove(&id);
+
+                if let Some((app_name, since)) = stuck.remove(&id) {
+                    log::warn!("{app_name} stopped after all, {:?} late", since.elapsed());
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                let now = Instant::now();
+                armed.retain(|id, (app_name, timeout, deadline)| {
+                    if *deadline > now {
+                        return true;
+                    }
+
+                    log::error!(
+                        "{app_name} didn't stop within {timeout:?}: it is stuck, and the daemon \
+                        can't respond until it stops"
+                    );
+                    stuck.insert(*id, (*app_name, *deadline));
+                    false
+                });
+            }
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+    }
+}
+
 #[derive(Debug, derive_more::Error, derive_more::Display, derive_more::From)]
 pub enum PolymodoError {
     #[display("no app with app key {_0} exists")]
     NoSuchApp(#[error(not(source))] app::AppKey),
+    #[display("the app's result is no longer available")]
+    ResultUnavailable(oneshot::RecvError),
 }
 
 #[derive(Clone)]
@@ -161,12 +471,34 @@ impl PolymodoHandle {
     /// Returns the associated app key.
     ///
     /// This method only exists on `PolymodoHandle`, as a new handle is created to pass onto the event loop.
-    pub fn spawn_app<A>(&self) -> anyhow::Result<app::AppKey>
+    pub fn spawn_app<A>(&self, options: &app::AppOptions) -> anyhow::Result<app::AppKey>
     where
         A: app::App + 'static,
         A::Message: Send + 'static,
         A::Output: AppResult + Send,
     {
+        // before our window takes focus. The compositor may be slow to answer: the app opens in
+        // the meantime.
+        let previous_focus = self.return_focus.then(|| smol::unblock(Toplevel::active));
+
+        // an app of this kind may be hidden, waiting to be reopened: that's much quicker than
+        // creating a new one.
+        self.stop_stale_hidden_apps();
+        let hidden_key = self
+            .hidden_apps
+            .borrow()
+            .iter()
+            .find(|(_, app)| app.app_name() == A::NAME)
+            .map(|(key, _)| *key);
+        if let Some(key) = hidden_key {
+            let mut app = self.hidden_apps.borrow_mut().remove(&key).unwrap();
+            app.reopen(options);
+            self.apps.borrow_mut().insert(key, app);
+            self.remember_focus(key, previous_focus);
+
+            return Ok(key);
+        }
+
         // create a new key for this app.
         // (it's just a number)
         let key = app::new_app_key();
@@ -174,7 +506,7 @@ impl PolymodoHandle {
         let handle = self.clone();
 
         // Create the app and its driver (wrapper)
-        let app = A::create(app_sender);
+        let app = A::create(app_sender, options);
         let driver = app::driver_for(app);
 
         // Add it to the list
@@ -182,17 +514,321 @@ impl PolymodoHandle {
         apps.insert(key, Box::new(driver));
         drop(apps);
 
+        self.remember_focus(key, previous_focus);
+
         Ok(key)
     }
 
-    pub fn start_running(&self) -> JoinHandle<std::convert::Infallible> {
+    /// Remember which window to give focus back to once the app with this key stops, once the
+    /// compositor has told which one that is.
+    fn remember_focus(&self, app_key: app::AppKey, toplevel: Option<smol::Task<Option<Toplevel>>>) {
+        let Some(toplevel) = toplevel else {
+            return;
+        };
+
+        let handle = self.clone();
+        drop(self.event_loop.spawn(async move {
+            let Some(toplevel) = toplevel.await else {
+                return;
+            };
+
+            // the app may have stopped before the compositor answered
+            if handle.apps.borrow().contains_key(&app_key) {
+                handle.previous_focus.borrow_mut().insert(app_key, toplevel);
+            }
+        }));
+    }
+
+    /// Receive one message from the messages or control channel (potentially waiting if there are
+    /// none) and forward it to the app it came from. Control events go first.
+    async fn handle_app_message(&self) {
+        let event = smol::future::or(
+            self.app_control_channel.1.recv(),
+            self.app_message_channel.1.recv(),
+        )
+        .await;
+        let Ok(AppEvent { app_key, message }) = event else {
+            // `recv` only returns an error if the channel is closed (impossible: polymodo holds a
+            // sender of both channels), thus this should really never happen.
+            unreachable!();
+        };
+
+        match message {
+            AppMessage::Finished => {
+                // check if anyone's listening for this app's result.
+                // this has to happen before stopping the app, as `stop_app` would tell them the
+                // result is unavailable otherwise.
+                let sender = self.app_finish_senders.borrow_mut().remove(&app_key);
+
+                let Ok(result) = self.stop_app(app_key).await else {
+                    log::error!("got a Finished message for an app that doesn't exist");
+                    return;
+                };
+
+                if let Some(sender) = sender {
+                    if sender.send(Some(result)).is_err() {
+                        log::warn!(
+                            "could not deliver app result because the receiver has been dropped"
+                        );
+                    }
+                } else {
+                    // no one's listening. do we want to log the result somehow?
+                    log::warn!("app finished, but no listener was registered for its result");
+                }
+            }
+            // hidden apps still receive these: they may have work going on in the background.
+            AppMessage::Message(message) => {
+                if self
+                    .with_app(app_key, |app| app.on_message(message))
+                    .is_none()
+                {
+                    // might happen if an app sends a message, but is stopped before that message ever gets processed.
+                    log::warn!("failed to send message to app, because app does not exist.");
+                }
+            }
+            AppMessage::SpawnLocal(abortable) => {
+                if self
+                    .with_app(app_key, |app| app.add_abortable(abortable))
+                    .is_none()
+                {
+                    log::warn!("cannot attach task to app, because app does not exist.");
+                }
+            }
+            AppMessage::SetBusy(busy) => {
+                if self.with_app(app_key, |app| app.set_busy(busy)).is_none() {
+                    log::warn!("cannot set busy state of app, because app does not exist.");
+                }
+            }
+            AppMessage::Shutdown => {
+                log::info!("app {app_key} requested polymodo to shut down");
+
+                // shutting down waits for all apps to stop, which requires this message loop to
+                // keep running, so it has to happen in a separate task.
+                let poly = self.clone();
+                drop(self.event_loop.spawn(async move { poly.shutdown().await }));
+            }
+        }
+    }
+
+    /// Ask all running apps to finish, wait for them to stop, and then quit the event loop.
+    pub async fn shutdown(&self) {
+        let keys: Vec<_> = self.apps.borrow().keys().copied().collect();
+        for key in keys {
+            self.finish_app(key);
+        }
+
+        self.wait_for_all_apps().await;
+
+        // hidden apps have finished already; stop them for good.
+        for (_, mut app) in self.hidden_apps.take() {
+            drop(app.stop());
+        }
+
+        (self.event_loop.quit)();
+    }
+
+    pub fn start_running(&self) -> Box<dyn Abortable + Send> {
         let poly = self.clone();
 
-        slint::spawn_local(async move {
+        self.event_loop.spawn(async move {
             loop {
                 poly.handle_app_message().await;
             }
         })
-        .expect("an event loop")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Polymodo, APP_EVENT_CAPACITY};
+    use crate::app::{AppMessage, AppName, AppOptions};
+    use crate::testing;
+    use std::time::Duration;
+
+    #[test]
+    fn open_apps_keep_polymodo_busy() {
+        testing::run(async {
+            let poly = testing::polymodo();
+
+            let key = poly
+                .spawn_app::<testing::FakeApp>(&AppOptions::default())
+                .unwrap();
+            assert_eq!(poly.idle_for(), None);
+
+            poly.stop_app(key).await.unwrap();
+            assert!(poly
+                .idle_for()
+                .is_some_and(|idle| idle < Duration::from_secs(1)));
+        });
+    }
+
+    #[test]
+    fn hidden_apps_are_reopened() {
+        testing::run(async {
+            testing::keep_fake_apps_warm(true);
+            let poly = testing::polymodo();
+
+            let key = poly
+                .spawn_app::<testing::FakeApp>(&AppOptions::default())
+                .unwrap();
+            poly.stop_app(key).await.unwrap();
+
+            assert_eq!(poly.app_count(), 0);
+            assert!(!poly.is_app_running(AppName::Fake).await);
+
+            let reopened = poly
+                .spawn_app::<testing::FakeApp>(&AppOptions::default())
+                .unwrap();
+
+            assert_eq!(reopened, key);
+            assert_eq!(poly.app_count(), 1);
+        });
+    }
+
+    #[test]
+    fn no_apps_are_hidden_without_room() {
+        testing::run(async {
+            testing::keep_fake_apps_warm(true);
+            let poly = Polymodo::with_event_loop(testing::TEST_EVENT_LOOP)
+                .with_max_hidden_apps(0)
+                .into_handle();
+
+            let key = poly
+                .spawn_app::<testing::FakeApp>(&AppOptions::default())
+                .unwrap();
+            poly.stop_app(key).await.unwrap();
+
+            assert_eq!(poly.hidden_app_count(), 0);
+        });
+    }
+    #[test]
+    fn hidden_apps_are_limited() {
+        testing::run(async {
+            testing::keep_fake_apps_warm(true);
+            let poly = testing::polymodo();
+
+            let first = poly
+                .spawn_app::<testing::FakeApp>(&AppOptions::default())
+                .unwrap();
+            let second = poly
+                .spawn_app::<testing::FakeApp>(&AppOptions::default())
+                .unwrap();
+            poly.stop_app(first).await.unwrap();
+            poly.stop_app(second).await.unwrap();
+
+            // only one of each kind is kept
+            assert_eq!(poly.hidden_app_count(), 1);
+        });
+    }
+
+    #[test]
+    fn stale_hidden_apps_are_replaced() {
+        testing::run(async {
+            testing::keep_fake_apps_warm(true);
+            let poly = testing::polymodo();
+
+            let key = poly
+                .spawn_app::<testing::FakeApp>(&AppOptions::default())
+                .unwrap();
+            poly.stop_app(key).await.unwrap();
+
+            testing::fake_apps_can_reopen(false);
+            let replacement = poly
+                .spawn_app::<testing::FakeApp>(&AppOptions::default())
+                .unwrap();
+            testing::fake_apps_can_reopen(true);
+
+            assert_ne!(replacement, key);
+            assert_eq!(poly.hidden_app_count(), 0);
+        });
+    }
+
+    #[test]
+    fn debounced_messages_replace_each_other() {
+        testing::run(async {
+            let poly = testing::polymodo();
+            let _run_task = poly.start_running();
+            let key = poly
+                .spawn_app::<testing::FakeApp>(&AppOptions::default())
+                .unwrap();
+            let sender = poly.app_sender::<String>(key);
+
+            // the fake app finishes with the first message it receives
+            for query in ["f", "fi", "fire"] {
+                sender.send_debounced(query.to_string(), Duration::from_millis(10));
+            }
+
+            let result = poly.wait_for_app_stop(key).await.unwrap().unwrap();
+            assert_eq!(result.to_json().unwrap(), r#""fire""#);
+        });
+    }
+
+    #[test]
+    fn full_channel_discards_messages() {
+        testing::run(async {
+            let poly = testing::polymodo();
+            let key = poly
+                .spawn_app::<testing::FakeApp>(&AppOptions::default())
+                .unwrap();
+            let sender = poly.app_sender::<String>(key);
+
+            // nothing receives while this runs, so the channel fills up
+            let sent = (0..=APP_EVENT_CAPACITY)
+                .take_while(|_| sender.send("spam".to_string()).is_ok())
+                .count();
+
+            assert!(sent <= APP_EVENT_CAPACITY);
+            assert!(sender.send("spam".to_string()).is_err());
+        });
+    }
+
+    #[test]
+    fn queued_messages_keep_their_order() {
+        testing::run(async {
+            let poly = testing::polymodo();
+            let key = poly
+                .spawn_app::<testing::FakeApp>(&AppOptions::default())
+                .unwrap();
+            let sender = poly.app_sender::<String>(key);
+            let receiver = &poly.app_message_channel.1;
+
+            while sender.send("spam".to_string()).is_ok() {}
+            sender.send_or_queue("fi".to_string());
+
+            // there is room again, but "fire" must still wait for "fi"
+            drop(receiver.recv().await.unwrap());
+            sender.clone().send_or_queue("fire".to_string());
+
+            let mut messages = Vec::new();
+            while messages.len() < 2 {
+                let AppMessage::Message(message) = receiver.recv().await.unwrap().message else {
+                    continue;
+                };
+                let message = *message.downcast::<String>().unwrap();
+                if message != "spam" {
+                    messages.push(message);
+                }
+            }
+
+            assert_eq!(messages, ["fi", "fire"]);
+        });
+    }
+
+    #[test]
+    fn full_channel_still_finishes_apps() {
+        testing::run(async {
+            let poly = testing::polymodo();
+            let key = poly
+                .spawn_app::<testing::FakeApp>(&AppOptions::default())
+                .unwrap();
+            let sender = poly.app_sender::<String>(key);
+
+            while sender.send("spam".to_string()).is_ok() {}
+            sender.finish();
+
+            // only the control event is handled here: the messages are still waiting
+            poly.handle_app_message().await;
+            assert_eq!(poly.app_count(), 0);
+        });
     }
 }
@@ -1,100 +1,656 @@
-use crate::ipc::{AppSpawnOptions, ClientboundMessage, IpcS2C, IpcServer, ServerboundMessage};
-use crate::mode::launch::Launcher;
-use crate::polymodo::{Polymodo, PolymodoHandle};
+use crate::app::{AppKey, AppName, AppOptions, AppResult, Screenshot};
+use crate::ipc::{
+    encoded_len, AppSpawnOptions, ClientboundMessage, ErrorCode, IpcReceiveError, IpcS2C,
+    IpcSendError, IpcServer, ServerboundMessage, ToggleAction, MAX_MESSAGE_SIZE,
+};
+use crate::polymodo::{Polymodo, PolymodoError, PolymodoHandle};
+use std::ops::ControlFlow;
+use std::time::{Duration, Instant};
+
+/// How long to wait before accepting connections again, after accepting one failed.
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
 
 #[derive(Debug, derive_more::Error, derive_more::Display, derive_more::From)]
-enum ServerError {
+pub enum ServerError {
     #[display("the server could not retrieve the app's result")]
     FailedToGetResult,
+    #[display("no {_0} app is running")]
+    AppNotRunning(#[error(not(source))] AppName),
+    #[display("failed to spawn {_0}: {_1}")]
+    SpawnFailed(AppName, #[error(not(source))] String),
+    #[display("{_0}")]
+    Polymodo(PolymodoError),
+    #[display("failed to encode the app's result: {_0}")]
+    ResultEncoding(#[error(not(source))] String),
+    #[display("couldn't take a screenshot of {_0}: {_1}")]
+    #[from(ignore)]
+    Screenshot(AppName, #[error(not(source))] String),
+    #[display("failed to send message: {_0}")]
+    Send(IpcSendError),
+    #[display("failed to receive message: {_0}")]
+    Receive(IpcReceiveError),
+}
+
+impl ServerError {
+    /// The error code reported to clients for this error.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            ServerError::FailedToGetResult | ServerError::ResultEncoding(_) => {
+                ErrorCode::ResultUnavailable
+            }
+            ServerError::SpawnFailed(..) => ErrorCode::SpawnFailed,
+            ServerError::AppNotRunning(_) => ErrorCode::NoSuchApp,
+            ServerError::Polymodo(PolymodoError::NoSuchApp(_)) => ErrorCode::NoSuchApp,
+            ServerError::Polymodo(PolymodoError::ResultUnavailable(_)) => {
+                ErrorCode::ResultUnavailable
+            }
+            ServerError::Send(_) | ServerError::Receive(_) => ErrorCode::Ipc,
+            ServerError::Screenshot(..) => ErrorCode::ScreenshotFailed,
+        }
+    }
+}
+
+/// Limits how many messages a single client may send in a given time window.
+#[derive(Debug, Copy, Clone)]
+pub struct RateLimit {
+    /// The maximum amount of messages a client may send within `window`
+    pub max_messages: u32,
+    pub window: Duration,
+    /// How long to stop reading from a client that exceeded the limit
+    pub penalty: Duration,
 }
 
-pub fn run_server() -> anyhow::Result<std::convert::Infallible> {
-    crate::setup_slint_backend();
+impl Default for RateLimit {
+    fn default() -> Self {
+        Self {
+            max_messages: 100,
+            window: Duration::from_secs(1),
+            penalty: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Run the polymodo daemon. This function returns once the daemon has been shut down, after all of
+/// its apps have stopped.
+pub fn run_server() -> anyhow::Result<()> {
+    crate::setup_slint_backend()?;
+    crate::mode::launch::register_caches();
+    // killed once the daemon stops, rather than outliving it
+    let _icon_theme_watch = crate::mode::launch::watch_system_icon_theme();
 
     // set up the polymodo daemon socket for clients to connect to
     let ipc_server = crate::ipc::create_ipc_server()?; // TODO: try? here is probably not good
 
     slint::invoke_from_event_loop(|| {
-        let poly = Polymodo::new().into_handle();
+        let options = crate::config::Options::load();
+        let poly = Polymodo::new()
+            .with_app_stop_timeout(options.app_stop_timeout)
+            .with_max_hidden_apps(options.max_hidden_apps)
+            .with_return_focus(options.return_focus)
+            .into_handle();
         let _run_task = poly.start_running();
 
-        let _server_task = slint::spawn_local(accept_clients(poly.clone(), ipc_server));
+        if let Some(timeout) = options.idle_timeout {
+            drop(
+                poly.event_loop()
+                    .spawn(shut_down_when_idle(poly.clone(), timeout)),
+            );
+        }
+        if let Some(max_age) = options.cache_max_age {
+            drop(poly.event_loop().spawn(expire_caches(max_age)));
+        }
+
+        let _server_task =
+            slint::spawn_local(accept_clients(poly.clone(), ipc_server, options.rate_limit));
+        let pressed = crate::shortcuts::register(options.shortcuts);
+        drop(
+            poly.event_loop()
+                .spawn(toggle_on_shortcuts(poly.clone(), pressed)),
+        );
 
-        let key = poly.spawn_app::<Launcher>().expect("failed to spawn app");
-        log::info!("spawned launcher with key {key}");
+        match spawn_app(&poly, AppName::Launcher, &AppOptions::default()) {
+            Ok(key) => log::info!("spawned launcher with key {key}"),
+            Err(e) => log::error!("{e}"),
+        }
     })
     .expect("an event loop");
 
     slint::run_event_loop_until_quit()?;
 
-    unreachable!()
+    log::info!("polymodo daemon stopped");
+
+    Ok(())
 }
 
-async fn accept_clients(polymodo: PolymodoHandle, ipc_server: IpcServer) {
+/// Shut the daemon down once it has been idle for `timeout`, see [Polymodo::idle_for]. The next
+/// invocation of polymodo becomes the daemon again.
+async fn shut_down_when_idle(polymodo: PolymodoHandle, timeout: Duration) {
     loop {
-        let Ok(client) = ipc_server.accept().await else {
-            continue;
+        let idle_for = polymodo.idle_for().unwrap_or_default();
+        if idle_for >= timeout {
+            break;
+        }
+
+        // it may have been used in the meantime: check again once it could have been idle long
+        // enough.
+        smol::Timer::after(timeout - idle_for).await;
+    }
+
+    log::info!("idle for {}s, shutting down", timeout.as_secs());
+    polymodo.shutdown().await;
+}
+
+/// Clear caches once they are `max_age` old, see [crate::cache::clear_expired].
+async fn expire_caches(max_age: Duration) {
+    loop {
+        // caches that are empty now may be filled at any time: check again once they could have
+        // expired.
+        let next_expiry = crate::cache::clear_expired(max_age).unwrap_or(max_age);
+
+        smol::Timer::after(next_expiry).await;
+    }
+}
+
+/// Toggle the app of every global shortcut that is pressed, like `polymodo --toggle` would, see
+/// [crate::shortcuts].
+async fn toggle_on_shortcuts(polymodo: PolymodoHandle, pressed: smol::channel::Receiver<AppName>) {
+    while let Ok(app_name) = pressed.recv().await {
+        polymodo.mark_active();
+
+        match toggle_app(&polymodo, app_name, &AppOptions::default()) {
+            Ok(Some(key)) => log::debug!("shortcut spawned {app_name} with key {key}"),
+            Ok(None) => log::debug!("shortcut closed {app_name}"),
+            Err(e) => log::error!("{e}"),
+        }
+    }
+}
+
+async fn accept_clients(polymodo: PolymodoHandle, ipc_server: IpcServer, rate_limit: RateLimit) {
+    loop {
+        let client = match ipc_server.accept().await {
+            Ok(client) => client,
+            Err(e) => {
+                // errors like EMFILE won't resolve themselves immediately:
+                // wait a bit instead of spinning on them.
+                log::error!("failed to accept connection: {e}");
+                smol::Timer::after(ACCEPT_BACKOFF).await;
+                continue;
+            }
         };
 
         log::debug!("accept new connection at {:?}", client.addr());
 
         // explicit drop: not interested in the return value of this task.
         // dropping it does not cancel the task
-        drop(slint::spawn_local(serve_client(polymodo.clone(), client)).expect("an event loop"));
+        drop(
+            polymodo
+                .event_loop()
+                .spawn(serve_client(polymodo.clone(), client, rate_limit)),
+        );
     }
 }
 
 /// Given an [IpcClient], perform the read loop, serving any requests made by the client.
-async fn serve_client(polymodo: PolymodoHandle, client: IpcS2C) {
+pub async fn serve_client(polymodo: PolymodoHandle, client: IpcS2C, rate_limit: RateLimit) {
+    // start of the current rate limiting window, and the amount of messages received within it
+    let mut last_message_time = Instant::now();
+    let mut message_count = 0u32;
+
     loop {
         let message = match client.recv().await {
-            Err(crate::ipc::IpcReceiveError::DecodeError(e)) => {
-                log::error!("could not decode message from client: {e}");
-                log::error!("this is fatal: aborting connection with client.");
-                return;
-            }
-            Err(crate::ipc::IpcReceiveError::IoError(e)) => {
-                log::error!("io error while reading from client: {e}");
+            Ok(m) => m,
+            Err(e) => {
+                log::error!("could not read message from client: {e}");
                 log::error!("this is fatal: aborting connection with client.");
+
+                // the connection might still be writable, so try letting the client know.
+                send_error(&client, e.into()).await;
                 return;
             }
-            Ok(m) => m,
         };
+        polymodo.mark_active();
 
-        let _ = match message {
-            ServerboundMessage::Ping => client.send(ClientboundMessage::Pong).await,
-            ServerboundMessage::Spawn(AppSpawnOptions { app_name, single }) => {
-                if single && polymodo.is_app_running(app_name).await {
-                    return;
-                }
+        if last_message_time.elapsed() > rate_limit.window {
+            last_message_time = Instant::now();
+            message_count = 0;
+        }
+        message_count += 1;
+
+        if message_count > rate_limit.max_messages {
+            log::warn!(
+                "client at {:?} exceeded {} messages per {:?}; throttling",
+                client.addr(),
+                rate_limit.max_messages,
+                rate_limit.window
+            );
+
+            let _ = client.send(ClientboundMessage::RateLimited).await;
+            smol::Timer::after(rate_limit.penalty).await;
 
-                let app_key = polymodo
-                    .spawn_app::<Launcher>()
-                    .expect("failed to spawn app"); // todo: no expect
-                let app_result = polymodo
-                    .wait_for_app_stop(app_key)
-                    .await
-                    .expect("sender closed"); // todo: no expect
+            last_message_time = Instant::now();
+            message_count = 0;
+        }
 
-                let result: anyhow::Result<_> = app_result
-                    .ok_or(ServerError::FailedToGetResult.into())
-                    .and_then(|result| result.to_json());
+        match handle_message(&polymodo, &client, message).await {
+            Ok(ControlFlow::Continue(())) => {}
+            Ok(ControlFlow::Break(())) => return,
+            Err(e) => {
+                log::error!("failed to handle client request: {e}");
+                send_error(&client, e).await;
+            }
+        }
+    }
+}
 
-                let result = result.unwrap_or_else(|e| format!("{e}"));
+/// Handle a single message from a client. Returns whether the connection should stay open.
+async fn handle_message(
+    polymodo: &PolymodoHandle,
+    client: &IpcS2C,
+    message: ServerboundMessage,
+) -> Result<ControlFlow<()>, ServerError> {
+    match message {
+        ServerboundMessage::Ping => client.send(ClientboundMessage::Pong).await?,
+        ServerboundMessage::Spawn(AppSpawnOptions {
+            app_name,
+            single,
+            options,
+        }) => {
+            if single && polymodo.is_app_running(app_name).await {
+                client.send(ClientboundMessage::AlreadyRunning).await?;
+                return Ok(ControlFlow::Continue(()));
+            }
 
-                if let Err(e) = client.send(ClientboundMessage::AppResult(result)).await {
-                    log::error!("failed to send result to client: {e}")
+            let app_key = spawn_app(polymodo, app_name, &options)?;
+            send_app_result(polymodo, client, app_key).await?;
+        }
+        ServerboundMessage::Toggle(app_name, options) => {
+            match toggle_app(polymodo, app_name, &options)? {
+                Some(app_key) => {
+                    client
+                        .send(ClientboundMessage::Toggled(ToggleAction::Opened))
+                        .await?;
+                    send_app_result(polymodo, client, app_key).await?;
                 }
+                None => {
+                    client
+                        .send(ClientboundMessage::Toggled(ToggleAction::Closed))
+                        .await?;
+                }
+            }
+        }
+        ServerboundMessage::Stop(app_key) => {
+            let app_result = polymodo.stop_app(app_key).await?;
+
+            client
+                .send(ClientboundMessage::AppResult(encode_result(app_result)?))
+                .await?;
+        }
+        ServerboundMessage::AppState(app_name) => {
+            let state = polymodo
+                .app_state(app_name)
+                .ok_or(ServerError::AppNotRunning(app_name))?;
+
+            client
+                .send(ClientboundMessage::AppState(state.to_string()))
+                .await?;
+        }
+        ServerboundMessage::Screenshot(app_name) => {
+            let screenshot = polymodo
+                .app_screenshot(app_name)
+                .ok_or(ServerError::AppNotRunning(app_name))?
+                .ok_or_else(|| {
+                    ServerError::Screenshot(app_name, "its window can't be captured".to_string())
+                })?;
+            let png = encode_png(&screenshot)
+                .map_err(|e| ServerError::Screenshot(app_name, e.to_string()))?;
+            let message = ClientboundMessage::Screenshot(png);
 
-                Ok(())
+            // the client would refuse it, and only say the message was too large
+            let len = encoded_len(&message)
+                .map_err(|e| ServerError::Screenshot(app_name, e.to_string()))?;
+            if len > MAX_MESSAGE_SIZE {
+                return Err(ServerError::Screenshot(
+                    app_name,
+                    format!(
+                        "the image is {len} bytes, more than the {MAX_MESSAGE_SIZE} bytes IPC \
+                        messages may have"
+                    ),
+                ));
             }
-            // this client is about to quit.
-            ServerboundMessage::Goodbye => {
-                log::debug!("closing connection at {:?}", client.addr());
-                let _ = client.shutdown().await;
 
-                return;
+            client.send(message).await?;
+        }
+        ServerboundMessage::CacheStatus => {
+            client
+                .send(ClientboundMessage::CacheStatus(crate::cache::status()))
+                .await?;
+        }
+        ServerboundMessage::ClearCache(kind) => {
+            client
+                .send(ClientboundMessage::CacheCleared(crate::cache::clear(kind)))
+                .await?;
+        }
+        ServerboundMessage::Health => {
+            client
+                .send(ClientboundMessage::HealthOk {
+                    uptime_secs: polymodo.uptime().as_secs(),
+                    app_count: polymodo.app_count() as u32,
+                })
+                .await?;
+        }
+        // this client is about to quit.
+        ServerboundMessage::Goodbye => {
+            log::debug!("closing connection at {:?}", client.addr());
+            let _ = client.shutdown().await;
+
+            return Ok(ControlFlow::Break(()));
+        }
+    }
+
+    Ok(ControlFlow::Continue(()))
+}
+
+/// Spawn the app corresponding to `app_name`.
+fn spawn_app(
+    polymodo: &PolymodoHandle,
+    app_name: AppName,
+    options: &AppOptions,
+) -> Result<AppKey, ServerError> {
+    crate::mode::spawn_app(polymodo, app_name, options)
+        .map_err(|e| ServerError::SpawnFailed(app_name, e.to_string()))
+}
+
+/// Stop the app corresponding to `app_name` if it is running, or spawn it if it isn't. Returns the
+/// key of the app if it was spawned.
+fn toggle_app(
+    polymodo: &PolymodoHandle,
+    app_name: AppName,
+    options: &AppOptions,
+) -> Result<Option<AppKey>, ServerError> {
+    match polymodo.running_app_key(app_name) {
+        Some(app_key) => {
+            polymodo.finish_app(app_key);
+            Ok(None)
+        }
+        None => spawn_app(polymodo, app_name, options).map(Some),
+    }
+}
+
+/// Wait for the app with `app_key` to stop, and send its result to the client.
+async fn send_app_result(
+    polymodo: &PolymodoHandle,
+    client: &IpcS2C,
+    app_key: AppKey,
+) -> Result<(), ServerError> {
+    let app_result = polymodo
+        .wait_for_app_stop(app_key)
+        .await?
+        .ok_or(ServerError::FailedToGetResult)?;
+
+    client
+        .send(ClientboundMessage::AppResult(encode_result(app_result)?))
+        .await?;
+
+    Ok(())
+}
+
+fn encode_result(app_result: Box<dyn AppResult + Send>) -> Result<String, ServerError> {
+    app_result
+        .to_json()
+        .map_err(|e| ServerError::ResultEncoding(e.to_string()))
+}
+
+/// Encode the pixels of a screenshot as a PNG image.
+fn encode_png(screenshot: &Screenshot) -> image::ImageResult<Vec<u8>> {
+    let mut png = Vec::new();
+    image::write_buffer_with_format(
+        &mut std::io::Cursor::new(&mut png),
+        screenshot.as_bytes(),
+        screenshot.width(),
+        screenshot.height(),
+        image::ExtendedColorType::Rgba8,
+        image::ImageFormat::Png,
+    )?;
+
+    Ok(png)
+}
+
+/// Report an error to the client. Failing to do so is logged, but otherwise ignored.
+async fn send_error(client: &IpcS2C, error: ServerError) {
+    let message = ClientboundMessage::Error {
+        code: error.code(),
+        message: error.to_string(),
+    };
+
+    if let Err(e) = client.send(message).await {
+        log::error!("failed to send error to client: {e}");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ipc::IpcC2S;
+    use crate::testing;
+    use smol::io::AsyncWriteExt;
+
+    fn spawn_fake(single: bool) -> ServerboundMessage {
+        ServerboundMessage::Spawn(AppSpawnOptions {
+            app_name: AppName::Fake,
+            single,
+            options: AppOptions::default(),
+        })
+    }
+
+    #[test]
+    fn spawn_replies_with_app_result() {
+        testing::run(async {
+            let poly = testing::polymodo();
+            let _run_task = poly.start_running();
+            let (client, server) = crate::ipc::pair().unwrap();
+
+            let serve = serve_client(poly.clone(), server, RateLimit::default());
+            let requests = async {
+                client.send(spawn_fake(false)).await.unwrap();
+
+                let key = testing::wait_for_app(&poly, AppName::Fake).await;
+                poly.app_sender::<String>(key)
+                    .send("done".to_string())
+                    .unwrap();
+
+                let reply = client.recv().await.unwrap();
+                client.send(ServerboundMessage::Goodbye).await.unwrap();
+
+                reply
+            };
+
+            let ((), reply) = smol::future::zip(serve, requests).await;
+
+            assert!(matches!(reply, ClientboundMessage::AppResult(result) if result == "\"done\""));
+            assert_eq!(poly.app_count(), 0);
+        });
+    }
+
+    #[test]
+    fn toggle_spawns_or_closes() {
+        testing::run(async {
+            let poly = testing::polymodo();
+            let _run_task = poly.start_running();
+
+            let key = toggle_app(&poly, AppName::Fake, &AppOptions::default()).unwrap();
+            assert_eq!(poly.running_app_key(AppName::Fake), key);
+            assert!(key.is_some());
+
+            let key = toggle_app(&poly, AppName::Fake, &AppOptions::default()).unwrap();
+            assert_eq!(key, None);
+        });
+    }
+
+    #[test]
+    fn single_spawn_is_suppressed_if_app_is_running() {
+        testing::run(async {
+            let poly = testing::polymodo();
+            let (client, server) = crate::ipc::pair().unwrap();
+
+            poly.spawn_app::<testing::FakeApp>(&AppOptions::default())
+                .unwrap();
+
+            client.send(spawn_fake(true)).await.unwrap();
+            client.send(ServerboundMessage::Goodbye).await.unwrap();
+            serve_client(poly.clone(), server, RateLimit::default()).await;
+
+            // the client is told, and nothing was spawned
+            assert!(matches!(
+                client.recv().await,
+                Ok(ClientboundMessage::AlreadyRunning)
+            ));
+            assert_eq!(poly.app_count(), 1);
+        });
+    }
+
+    #[test]
+    fn goodbye_closes_connection() {
+        testing::run(async {
+            let poly = testing::polymodo();
+            let (client, server) = crate::ipc::pair().unwrap();
+
+            client.send(ServerboundMessage::Ping).await.unwrap();
+            client.send(ServerboundMessage::Goodbye).await.unwrap();
+            serve_client(poly, server, RateLimit::default()).await;
+
+            assert!(matches!(client.recv().await, Ok(ClientboundMessage::Pong)));
+            assert!(client.recv().await.is_err());
+        });
+    }
+
+    #[test]
+    fn replies_are_delivered_before_shutdown() {
+        testing::run(async {
+            let poly = testing::polymodo();
+            let (client, server) = crate::ipc::pair().unwrap();
+
+            for _ in 0..50 {
+                client.send(ServerboundMessage::Ping).await.unwrap();
             }
-        };
+            client.send(ServerboundMessage::Goodbye).await.unwrap();
+            serve_client(poly, server, RateLimit::default()).await;
+
+            for _ in 0..50 {
+                assert!(matches!(client.recv().await, Ok(ClientboundMessage::Pong)));
+            }
+            assert!(client.recv().await.is_err());
+        });
+    }
+
+    #[test]
+    fn health_reports_running_apps() {
+        testing::run(async {
+            let poly = testing::polymodo();
+            let (client, server) = crate::ipc::pair().unwrap();
+
+            poly.spawn_app::<testing::FakeApp>(&AppOptions::default())
+                .unwrap();
+
+            client.send(ServerboundMessage::Health).await.unwrap();
+            client.send(ServerboundMessage::Goodbye).await.unwrap();
+            serve_client(poly, server, RateLimit::default()).await;
+
+            assert!(matches!(
+                client.recv().await,
+                Ok(ClientboundMessage::HealthOk { app_count: 1, .. })
+            ));
+        });
+    }
+
+    #[test]
+    fn app_state_of_app_that_is_not_running_is_an_error() {
+        testing::run(async {
+            let poly = testing::polymodo();
+            let (client, server) = crate::ipc::pair().unwrap();
+
+            client
+                .send(ServerboundMessage::AppState(AppName::Fake))
+                .await
+                .unwrap();
+            client.send(ServerboundMessage::Goodbye).await.unwrap();
+            serve_client(poly, server, RateLimit::default()).await;
+
+            assert!(matches!(
+                client.recv().await,
+                Ok(ClientboundMessage::Error {
+                    code: ErrorCode::NoSuchApp,
+                    ..
+                })
+            ));
+        });
+    }
+
+    #[test]
+    fn idle_daemon_shuts_down() {
+        testing::run(async {
+            testing::keep_fake_apps_warm(true);
+            let poly = testing::polymodo();
+
+            let key = poly
+                .spawn_app::<testing::FakeApp>(&AppOptions::default())
+                .unwrap();
+            poly.stop_app(key).await.unwrap();
+            assert_eq!(poly.hidden_app_count(), 1);
+
+            shut_down_when_idle(poly.clone(), Duration::from_millis(10)).await;
+
+            assert!(poly.idle_for().unwrap() >= Duration::from_millis(10));
+            assert_eq!(poly.hidden_app_count(), 0);
+        });
+    }
+
+    #[test]
+    fn screenshot_of_app_without_window_is_an_error() {
+        testing::run(async {
+            let poly = testing::polymodo();
+            let (client, server) = crate::ipc::pair().unwrap();
+
+            poly.spawn_app::<testing::FakeApp>(&AppOptions::default())
+                .unwrap();
+
+            client
+                .send(ServerboundMessage::Screenshot(AppName::Fake))
+                .await
+                .unwrap();
+            client.send(ServerboundMessage::Goodbye).await.unwrap();
+            serve_client(poly, server, RateLimit::default()).await;
+
+            assert!(matches!(
+                client.recv().await,
+                Ok(ClientboundMessage::Error {
+                    code: ErrorCode::ScreenshotFailed,
+                    ..
+                })
+            ));
+        });
+    }
+
+    #[test]
+    fn undecodable_message_aborts_connection() {
+        testing::run(async {
+            let poly = testing::polymodo();
+            let (mut stream, server) = smol::net::unix::UnixStream::pair().unwrap();
+
+            // there is no ServerboundMessage variant with this index
+            stream.write_all(&[200]).await.unwrap();
+
+            let client = IpcC2S::from_stream(stream).unwrap();
+            let server = IpcS2C::from_stream(server).unwrap();
+            serve_client(poly, server, RateLimit::default()).await;
+
+            assert!(matches!(
+                client.recv().await,
+                Ok(ClientboundMessage::Error {
+                    code: ErrorCode::Ipc,
+                    ..
+                })
+            ));
+            assert!(client.recv().await.is_err());
+        });
     }
 }
@@ -0,0 +1,137 @@
+//! Global shortcuts, registered with the compositor through `hyprland-global-shortcuts-v1`, which
+//! toggle apps in the daemon without a client process being started for every key press.
+//!
+//! Only Hyprland offers this protocol; on other compositors, bind `polymodo --toggle` instead.
+//! Shortcuts are registered on a Wayland connection of their own, as slint owns the one polymodo's
+//! windows are on.
+
+use crate::app::AppName;
+use anyhow::Context;
+use indexmap::IndexMap;
+use protocol::hyprland_global_shortcut_v1::{self, HyprlandGlobalShortcutV1};
+use protocol::hyprland_global_shortcuts_manager_v1::HyprlandGlobalShortcutsManagerV1;
+use wayland_client::globals::{registry_queue_init, GlobalListContents};
+use wayland_client::protocol::wl_registry::{self, WlRegistry};
+use wayland_client::{Connection, Dispatch, QueueHandle};
+
+/// The app id shortcuts are registered with: Hyprland binds them as `polymodo:<id>`.
+const APP_ID: &str = "polymodo";
+
+#[allow(non_upper_case_globals, clippy::all)]
+mod protocol {
+    use wayland_client;
+
+    pub mod __interfaces {
+        wayland_scanner::generate_interfaces!("protocols/hyprland-global-shortcuts-v1.xml");
+    }
+    use self::__interfaces::*;
+
+    wayland_scanner::generate_client_code!("protocols/hyprland-global-shortcuts-v1.xml");
+}
+
+/// Register `shortcuts`, each by its id and the app it toggles. The app of every shortcut that is
+/// pressed is sent to the returned receiver.
+///
+/// Shortcuts are registered, and listened to, on a thread of their own. Failing to register them,
+/// e.g. because the compositor isn't Hyprland, is logged; the receiver is then closed.
+pub fn register(shortcuts: IndexMap<String, AppName>) -> smol::channel::Receiver<AppName> {
+    let (sender, receiver) = smol::channel::unbounded();
+
+    if shortcuts.is_empty() {
+        return receiver;
+    }
+
+    let listener = std::thread::Builder::new()
+        .name("global shortcuts".to_string())
+        .spawn(move || {
+            if let Err(e) = listen(shortcuts, sender) {
+                log::warn!("couldn't register global shortcuts: {e:#}");
+            }
+        });
+    if let Err(e) = listener {
+        log::error!("couldn't start listening to global shortcuts: {e}");
+    }
+
+    receiver
+}
+
+/// Register `shortcuts`, and send the app of each one that is pressed to `pressed`, for as long as
+/// the compositor is connected to.
+fn listen(
+    shortcuts: IndexMap<String, AppName>,
+    pressed: smol::channel::Sender<AppName>,
+) -> anyhow::Result<()> {
+    let connection = Connection::connect_to_env()?;
+    let (globals, mut queue) = registry_queue_init::<Shortcuts>(&connection)?;
+    let queue_handle = queue.handle();
+
+    let manager: HyprlandGlobalShortcutsManagerV1 = globals
+        .bind(&queue_handle, 1..=1, ())
+        .context("the compositor doesn't support hyprland-global-shortcuts-v1")?;
+
+    for (id, app_name) in shortcuts {
+        log::debug!("registering global shortcut {APP_ID}:{id} for {app_name}");
+
+        manager.register_shortcut(
+            id,
+            APP_ID.to_string(),
+            format!("Toggle {app_name}"),
+            String::new(),
+            &queue_handle,
+            app_name,
+        );
+    }
+
+    let mut shortcuts = Shortcuts { pressed };
+    loop {
+        queue.blocking_dispatch(&mut shortcuts)?;
+    }
+}
+
+/// Where the events of the shortcuts' Wayland connection are dispatched to.
+struct Shortcuts {
+    pressed: smol::channel::Sender<AppName>,
+}
+
+impl Dispatch<WlRegistry, GlobalListContents> for Shortcuts {
+    fn event(
+        _: &mut Self,
+        _: &WlRegistry,
+        _: wl_registry::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        // the manager is bound once; globals that come and go after that don't matter.
+    }
+}
+
+impl Dispatch<HyprlandGlobalShortcutsManagerV1, ()> for Shortcuts {
+    fn event(
+        _: &mut Self,
+        _: &HyprlandGlobalShortcutsManagerV1,
+        _: <HyprlandGlobalShortcutsManagerV1 as wayland_client::Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        // the manager has no events.
+    }
+}
+
+impl Dispatch<HyprlandGlobalShortcutV1, AppName> for Shortcuts {
+    fn event(
+        shortcuts: &mut Self,
+        _: &HyprlandGlobalShortcutV1,
+        event: hyprland_global_shortcut_v1::Event,
+        app_name: &AppName,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        // toggle as soon as the keys are pressed; releasing them does nothing.
+        if let hyprland_global_shortcut_v1::Event::Pressed { .. } = event {
+            // the channel is unbounded: this only fails once the daemon stopped listening.
+            let _ = shortcuts.pressed.try_send(*app_name);
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! Utilities for testing polymodo without a windowing system.
+
+use crate::app::{Abortable, App, AppKey, AppName, AppOptions, AppSender, JsonAppResult};
+use crate::polymodo::{EventLoop, Polymodo, PolymodoHandle};
+use smol::LocalExecutor;
+use std::cell::Cell;
+use std::future::Future;
+use std::sync::Mutex;
+
+thread_local! {
+    static EXECUTOR: LocalExecutor<'static> = const { LocalExecutor::new() };
+    static KEEP_FAKE_APPS_WARM: Cell<bool> = const { Cell::new(false) };
+    static FAKE_APPS_CAN_REOPEN: Cell<bool> = const { Cell::new(true) };
+}
+
+/// An [EventLoop] backed by a thread-local executor, which is driven by [run].
+pub const TEST_EVENT_LOOP: EventLoop = EventLoop {
+    spawn_local: |future| {
+        let task = EXECUTOR.with(|executor| executor.spawn(future));
+
+        Box::new(TestTask(Mutex::new(Some(task))))
+    },
+    quit: || {},
+};
+
+/// Run `future` to completion, along with any tasks spawned on [TEST_EVENT_LOOP] meanwhile.
+pub fn run<T>(future: impl Future<Output = T> + 'static) -> T {
+    EXECUTOR.with(|executor| smol::block_on(executor.run(future)))
+}
+
+/// A polymodo instance running on [TEST_EVENT_LOOP].
+pub fn polymodo() -> PolymodoHandle {
+    Polymodo::with_event_loop(TEST_EVENT_LOOP).into_handle()
+}
+
+/// Wait until an app with this `app_name` is running, and return its key.
+pub async fn wait_for_app(polymodo: &PolymodoHandle, app_name: AppName) -> AppKey {
+    loop {
+        if let Some(key) = polymodo.running_app_key(app_name) {
+            return key;
+        }
+
+        smol::future::yield_now().await;
+    }
+}
+
+/// Make [FakeApp]s created on this thread hide, rather than stop, when they finish.
+pub fn keep_fake_apps_warm(warm: bool) {
+    KEEP_FAKE_APPS_WARM.set(warm);
+}
+
+/// Make hidden [FakeApp]s on this thread say whether they can be reopened, see
+/// [App::can_reopen].
+pub fn fake_apps_can_reopen(can_reopen: bool) {
+    FAKE_APPS_CAN_REOPEN.set(can_reopen);
+}
+
+/// Like slint's `JoinHandle`, dropping this detaches the task rather than cancelling it.
+struct TestTask(Mutex<Option<smol::Task<()>>>);
+
+impl Abortable for TestTask {
+    fn abort(&self) {
+        drop(self.0.lock().unwrap().take());
+    }
+}
+
+impl Drop for TestTask {
+    fn drop(&mut self) {
+        if let Some(task) = self.0.get_mut().unwrap().take() {
+            task.detach();
+        }
+    }
+}
+
+/// An app without a UI, which finishes with the first message it receives as its output.
+pub struct FakeApp {
+    sender: AppSender<String>,
+    output: Option<String>,
+}
+
+impl App for FakeApp {
+    type Message = String;
+    type Output = JsonAppResult<Option<String>>;
+
+    const NAME: AppName = AppName::Fake;
+
+    fn create(message_sender: AppSender<Self::Message>, _options: &AppOptions) -> Self {
+        Self {
+            sender: message_sender,
+            output: None,
+        }
+    }
+
+    fn on_message(&mut self, message: Self::Message) {
+        self.output = Some(message);
+        self.sender.finish();
+    }
+
+    fn hide(&mut self) -> Option<Self::Output> {
+        KEEP_FAKE_APPS_WARM
+            .get()
+            .then(|| JsonAppResult(self.output.take()))
+    }
+
+    fn can_reopen(&self) -> bool {
+        FAKE_APPS_CAN_REOPEN.get()
+    }
+
+    fn stop(self) -> Self::Output {
+        JsonAppResult(self.output)
+    }
+}
@@ -58,6 +58,23 @@ impl<K, V> IndexModel<K, V> {
 
         Some(r)
     }
+
+    pub fn remove<Q>(&self, key: &Q) -> Option<V>
+    where
+        Q: ?Sized + Hash + indexmap::Equivalent<K>,
+    {
+        let (row, _, value) = self.map.borrow_mut().shift_remove_full(key)?;
+
+        self.notify.row_removed(row, 1);
+
+        Some(value)
+    }
+
+    pub fn clear(&self) {
+        self.map.borrow_mut().clear();
+
+        self.notify.reset();
+    }
 }
 
 impl<K: Clone, V> IndexModel<K, V> {
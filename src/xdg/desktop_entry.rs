@@ -1,4 +1,6 @@
 use anyhow::Context;
+use indexmap::map::Entry;
+use indexmap::IndexMap;
 use ini::Ini;
 use std::hash::{Hash, Hasher};
 use std::path::{Path, PathBuf};
@@ -9,6 +11,12 @@ use std::path::{Path, PathBuf};
 pub struct DesktopEntry {
     /// The original path at which this desktop entry is located.
     pub source_path: PathBuf,
+    /// The desktop file id, like `org.mozilla.firefox.desktop`. Entries in different data dirs
+    /// with the same id are the same application.
+    pub id: String,
+    /// The path of the entry with the same id that this one takes precedence over, e.g. a
+    /// system-wide entry the user copied to their own data dir to change it.
+    pub overrides: Option<PathBuf>,
     /// The hash of the desktop entry's content
     pub source_hash: u64,
     pub entry_type: ApplicationType,
@@ -18,6 +26,16 @@ pub struct DesktopEntry {
     pub comment: Option<String>,
     pub icon: Option<String>,
     pub no_display: Option<bool>,
+    /// The WM class (or app ID) of the windows this entry opens, for matching them to it.
+    pub startup_wm_class: Option<String>,
+    /// The menu categories this entry belongs to, like `Network` and `WebBrowser`.
+    pub categories: Vec<String>,
+    /// Extra words this entry may be found by, besides its name.
+    pub keywords: Vec<String>,
+    /// The MIME types of the files this entry can open, like `application/pdf` or `image/*`.
+    pub mime_types: Vec<String>,
+    /// Extra environment variables to launch this entry with, read from `X-Polymodo-Env`.
+    pub env: Vec<(String, String)>,
 }
 
 #[derive(Copy, Clone, Debug, strum::EnumString)]
@@ -32,13 +50,19 @@ impl DesktopEntry {}
 pub fn load(path: impl AsRef<Path>) -> anyhow::Result<DesktopEntry> {
     let path = path.as_ref();
     let content = std::fs::read_to_string(path)?;
+
+    parse(path, &content)
+}
+
+/// Parse the `content` of the desktop entry at `path`.
+fn parse(path: &Path, content: &str) -> anyhow::Result<DesktopEntry> {
     let hash = {
         let mut hasher = std::hash::DefaultHasher::new();
         content.hash(&mut hasher);
         hasher.finish()
     };
 
-    let ini = Ini::load_from_str(&content)?;
+    let ini = Ini::load_from_str(content)?;
     let main_section = ini
         .section(Some("Desktop Entry"))
         .context("desktop entry does not have a Desktop Entry section")?;
@@ -55,9 +79,32 @@ pub fn load(path: impl AsRef<Path>) -> anyhow::Result<DesktopEntry> {
     let exec = main_section.get("Exec");
     let icon = main_section.get("Icon");
     let no_display = main_section.get("NoDisplay").and_then(|s| s.parse().ok());
+    let startup_wm_class = main_section.get("StartupWMClass");
+    let categories = main_section
+        .get("Categories")
+        .map(parse_list)
+        .unwrap_or_default();
+    let keywords = main_section
+        .get("Keywords")
+        .map(parse_list)
+        .unwrap_or_default();
+    let mime_types = main_section
+        .get("MimeType")
+        .map(parse_list)
+        .unwrap_or_default();
+    let env = main_section
+        .get("X-Polymodo-Env")
+        .map(parse_env)
+        .unwrap_or_default();
 
     Ok(DesktopEntry {
         source_path: path.to_path_buf(),
+        // without knowing the data dir it is in, the file name is the best guess
+        id: path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        overrides: None,
         source_hash: hash,
         entry_type,
         name: name.to_string(),
@@ -66,9 +113,35 @@ pub fn load(path: impl AsRef<Path>) -> anyhow::Result<DesktopEntry> {
         comment: comment.map(|s| s.to_string()),
         icon: icon.map(|s| s.to_string()),
         no_display,
+        startup_wm_class: startup_wm_class.map(|s| s.to_string()),
+        categories,
+        keywords,
+        mime_types,
+        env,
     })
 }
 
+/// Parse a semicolon-separated list of strings, like `Categories`. Empty items are ignored.
+fn parse_list(value: &str) -> Vec<String> {
+    value
+        .split(';')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Parse a semicolon-separated list of `KEY=VALUE` pairs.
+/// Malformed pairs (no `=`, or an empty key) are ignored.
+fn parse_env(value: &str) -> Vec<(String, String)> {
+    value
+        .split(';')
+        .filter_map(|pair| pair.split_once('='))
+        .filter(|(key, _)| !key.trim().is_empty())
+        .map(|(key, value)| (key.trim().to_string(), value.to_string()))
+        .collect()
+}
+
 struct DesktopEntryIdentifier<'a> {
     base_dir: &'a Path,
     entry: walkdir::DirEntry,
@@ -78,6 +151,18 @@ impl DesktopEntryIdentifier<'_> {
     fn relative_dir(&self) -> Option<&Path> {
         self.entry.path().strip_prefix(self.base_dir).ok()
     }
+
+    /// The desktop file id: the path relative to the `applications` dir, with slashes replaced
+    /// by dashes, like `kde-okular.desktop` for `kde/okular.desktop`.
+    fn id(&self) -> Option<String> {
+        let components = self
+            .relative_dir()?
+            .iter()
+            .map(|component| component.to_string_lossy())
+            .collect::<Vec<_>>();
+
+        Some(components.join("-"))
+    }
 }
 
 fn find_desktop_entries_in_base_dir(
@@ -108,16 +193,170 @@ pub fn find_desktop_entries() -> Vec<DesktopEntry> {
         dir.push("applications");
     }
 
-    let mut desktop_entries = data_dirs
+    find_desktop_entries_in(&data_dirs)
+}
+
+/// Find the desktop entries in `applications_dirs`, most important first.
+///
+/// Of entries with the same desktop file id, only the one in the most important dir is kept, so
+/// that e.g. a user's copy of a system-wide entry replaces it, rather than showing up next to it.
+fn find_desktop_entries_in(applications_dirs: &[PathBuf]) -> Vec<DesktopEntry> {
+    // desktop file id -> (path of the entry that is kept, path of the first entry it overrides)
+    let mut found = IndexMap::<String, (PathBuf, Option<PathBuf>)>::new();
+
+    for e in applications_dirs
         .iter()
-        .flat_map(|dd| find_desktop_entries_in_base_dir(dd))
-        .collect::<Vec<_>>();
+        .flat_map(|dir| find_desktop_entries_in_base_dir(dir))
+    {
+        let Some(id) = e.id() else {
+            continue;
+        };
+
+        match found.entry(id) {
+            Entry::Occupied(mut occupied) => {
+                let (kept, overrides) = occupied.get_mut();
+                log::trace!("{} overrides {}", kept.display(), e.entry.path().display());
 
-    // remove duplicate entries
-    desktop_entries.dedup_by_key(|e| e.relative_dir().map(|d| d.to_owned()));
+                overrides.get_or_insert_with(|| e.entry.path().to_path_buf());
+            }
+            Entry::Vacant(vacant) => {
+                vacant.insert((e.entry.path().to_path_buf(), None));
+            }
+        }
+    }
 
-    desktop_entries
+    found
         .into_iter()
-        .filter_map(|e| load(e.entry.path()).ok())
-        .collect::<Vec<_>>()
+        .filter_map(|(id, (path, overrides))| {
+            let entry = load(path).ok()?;
+
+            Some(DesktopEntry {
+                id,
+                overrides,
+                ..entry
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn startup_wm_class_is_parsed() {
+        let content = "[Desktop Entry]\n\
+            Type=Application\n\
+            Name=Firefox\n\
+            Exec=firefox %u\n\
+            StartupWMClass=firefox\n";
+
+        let entry = parse(Path::new("firefox.desktop"), content).unwrap();
+        assert_eq!(entry.startup_wm_class.as_deref(), Some("firefox"));
+
+        let content = "[Desktop Entry]\nType=Application\nName=Firefox\n";
+
+        let entry = parse(Path::new("firefox.desktop"), content).unwrap();
+        assert_eq!(entry.startup_wm_class, None);
+    }
+
+    #[test]
+    fn categories_and_keywords_are_parsed() {
+        let content = "[Desktop Entry]\n\
+            Type=Application\n\
+            Name=Firefox\n\
+            Categories=Network;WebBrowser;\n\
+            Keywords=web;internet;\n\
+            MimeType=text/html;x-scheme-handler/https;\n";
+
+        let entry = parse(Path::new("firefox.desktop"), content).unwrap();
+        assert_eq!(entry.categories, ["Network", "WebBrowser"]);
+        assert_eq!(entry.keywords, ["web", "internet"]);
+        assert_eq!(entry.mime_types, ["text/html", "x-scheme-handler/https"]);
+    }
+
+    #[test]
+    fn env_is_parsed() {
+        let content = "[Desktop Entry]\n\
+            Type=Application\n\
+            Name=Firefox\n\
+            X-Polymodo-Env=MOZ_ENABLE_WAYLAND=1; GDK_SCALE =2;NO_EQUALS;=empty;  =blank;URL=a=b;\n";
+
+        let entry = parse(Path::new("firefox.desktop"), content).unwrap();
+        let env = |key: &str, value: &str| (key.to_string(), value.to_string());
+        assert_eq!(
+            entry.env,
+            [
+                env("MOZ_ENABLE_WAYLAND", "1"),
+                // keys are trimmed, values are taken as they are
+                env("GDK_SCALE", "2"),
+                // only the first `=` separates the key from the value
+                env("URL", "a=b"),
+            ]
+        );
+    }
+
+    /// `tests/fixtures/data-dirs/<name>/applications`
+    fn fixture_dir(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/data-dirs")
+            .join(name)
+            .join("applications")
+    }
+
+    fn find_fixture_entries() -> Vec<DesktopEntry> {
+        find_desktop_entries_in(&[fixture_dir("home"), fixture_dir("system")])
+    }
+
+    #[test]
+    fn user_overrides_replace_system_entries() {
+        let entries = find_fixture_entries();
+        let firefox = entries
+            .iter()
+            .filter(|e| e.id == "firefox.desktop")
+            .collect::<Vec<_>>();
+
+        let [firefox] = firefox[..] else {
+            panic!("expected a single firefox entry, got {firefox:?}");
+        };
+        assert_eq!(
+            firefox.source_path,
+            fixture_dir("home").join("firefox.desktop")
+        );
+        assert_eq!(firefox.exec.as_deref(), Some("firefox --private-window %u"));
+        assert_eq!(
+            firefox.overrides,
+            Some(fixture_dir("system").join("firefox.desktop"))
+        );
+    }
+
+    #[test]
+    fn ids_include_subdirectories() {
+        let entries = find_fixture_entries();
+
+        // `kde/okular.desktop` has the id `kde-okular.desktop`, which the user overrides
+        let okular = entries
+            .iter()
+            .find(|e| e.id == "kde-okular.desktop")
+            .unwrap();
+        assert_eq!(
+            okular.source_path,
+            fixture_dir("home").join("kde-okular.desktop")
+        );
+        assert_eq!(
+            okular.overrides,
+            Some(fixture_dir("system").join("kde/okular.desktop"))
+        );
+        assert_eq!(entries.iter().filter(|e| e.name == "Okular").count(), 1);
+    }
+
+    #[test]
+    fn entries_without_override_are_kept() {
+        let entries = find_fixture_entries();
+
+        let gimp = entries.iter().find(|e| e.id == "gimp.desktop").unwrap();
+        assert_eq!(gimp.source_path, fixture_dir("system").join("gimp.desktop"));
+        assert_eq!(gimp.overrides, None);
+        assert_eq!(entries.len(), 3);
+    }
 }
@@ -0,0 +1,83 @@
+//! Which applications open which files, as far as the desktop is concerned.
+//!
+//! The MIME database and `mimeapps.list` are asked through `xdg-mime`, which knows about the
+//! quirks of every desktop, rather than read directly.
+
+use anyhow::Context;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// The MIME type of the file at `path`, like `application/pdf`.
+pub fn mime_type_of(path: &Path) -> Option<String> {
+    let mut command = Command::new("xdg-mime");
+    command.args(["query", "filetype"]).arg(path);
+
+    query(command)
+}
+
+/// The desktop file id of the application that opens files of `mime_type` by default, if any.
+pub fn default_application(mime_type: &str) -> Option<String> {
+    let mut command = Command::new("xdg-mime");
+    command.args(["query", "default", mime_type]);
+
+    query(command)
+}
+
+/// Open files of `mime_type` with the application with desktop file id `desktop_id` from now on,
+/// by writing it to the user's `mimeapps.list`.
+pub fn set_default_application(mime_type: &str, desktop_id: &str) -> anyhow::Result<()> {
+    let status = Command::new("xdg-mime")
+        .args(["default", desktop_id, mime_type])
+        .status()
+        .context("couldn't run xdg-mime")?;
+
+    anyhow::ensure!(status.success(), "xdg-mime exited with {status}");
+
+    Ok(())
+}
+
+/// Whether an application that lists `supported` in its `MimeType` can open files of `mime_type`.
+///
+/// Besides exact matches, wildcards like `image/*` are understood.
+pub fn can_open(supported: &[String], mime_type: &str) -> bool {
+    let media_type = mime_type.split_once('/').map(|(media_type, _)| media_type);
+
+    supported.iter().any(|supported| {
+        supported.eq_ignore_ascii_case(mime_type)
+            || supported
+                .strip_suffix("/*")
+                .is_some_and(|wildcard| Some(wildcard) == media_type)
+    })
+}
+
+/// Run `command`, and return the first line it prints if it succeeded and printed anything.
+fn query(mut command: Command) -> Option<String> {
+    let output = command
+        .stderr(Stdio::null())
+        .output()
+        .inspect_err(|e| log::warn!("couldn't run xdg-mime: {e}"))
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let line = stdout.lines().next()?.trim();
+
+    (!line.is_empty()).then(|| line.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn mime_types_are_matched() {
+        let supported = ["application/pdf".to_string(), "image/*".to_string()];
+
+        assert!(can_open(&supported, "application/pdf"));
+        assert!(can_open(&supported, "image/png"));
+        assert!(!can_open(&supported, "application/zip"));
+        assert!(!can_open(&supported, "imagery/png"));
+    }
+}
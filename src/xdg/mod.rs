@@ -1,3 +1,4 @@
 pub use desktop_entry::*;
 
 mod desktop_entry;
+pub mod mime;